@@ -0,0 +1,134 @@
+use std::collections::{HashMap, HashSet};
+
+use html5ever::LocalName;
+use kuchiki::Attributes;
+
+
+const GLOBAL: [&str; 2] = ["lang", "dir"];
+
+const PER_TAG: [(&str, &[&str]); 23] = [
+    ("a", &["href", "title", "rel"]),
+    ("img", &["src", "srcset", "alt", "width", "height"]),
+    ("source", &["src", "srcset", "type", "media"]),
+    ("video", &["src", "poster", "width", "height", "controls"]),
+    ("audio", &["src", "controls"]),
+    ("iframe", &["src", "width", "height", "allowfullscreen"]),
+    ("embed", &["src", "type", "width", "height"]),
+    ("object", &["data", "type", "width", "height"]),
+    ("td", &["colspan", "rowspan"]),
+    ("th", &["colspan", "rowspan", "scope"]),
+    ("col", &["span"]),
+    ("colgroup", &["span"]),
+    ("time", &["datetime"]),
+    ("abbr", &["title"]),
+    ("ol", &["start", "reversed", "type"]),
+    ("li", &["value"]),
+    ("blockquote", &["cite"]),
+    ("q", &["cite"]),
+    ("code", &["class"]),
+    ("del", &["cite", "datetime"]),
+    ("ins", &["cite", "datetime"]),
+    ("math", &["display"]),
+    ("annotation", &["encoding"]),
+];
+
+
+/// Attributes kept on elements, all other ones are removed.
+///
+/// The default list keeps only attributes needed to render the content, e.g. `href` of links
+/// and `src` of images, but neither `class` (except for the language of code), `id` nor event handlers.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AttributeAllowlist {
+    global: HashSet<LocalName>,
+    per_tag: HashMap<LocalName, HashSet<LocalName>>,
+}
+
+impl AttributeAllowlist {
+    /// Creates an empty list, which removes all attributes.
+    pub fn new() -> AttributeAllowlist {
+        AttributeAllowlist {
+            global: HashSet::new(),
+            per_tag: HashMap::new(),
+        }
+    }
+
+    /// Allows the attribute on the tag, use `*` to allow it on any tag.
+    pub fn allow(&mut self, tag: &str, attr: &str) -> &mut Self {
+        let attr = LocalName::from(attr.to_ascii_lowercase());
+
+        if tag == "*" {
+            self.global.insert(attr);
+        } else {
+            self.per_tag.entry(LocalName::from(tag.to_ascii_lowercase())).or_default().insert(attr);
+        }
+
+        self
+    }
+
+    /// Disallows the attribute on the tag, use `*` to disallow it on any tag.
+    pub fn disallow(&mut self, tag: &str, attr: &str) -> &mut Self {
+        let attr = LocalName::from(attr.to_ascii_lowercase());
+
+        if tag == "*" {
+            self.global.remove(&attr);
+
+            for attrs in self.per_tag.values_mut() {
+                attrs.remove(&attr);
+            }
+        } else if let Some(attrs) = self.per_tag.get_mut(&LocalName::from(tag.to_ascii_lowercase())) {
+            attrs.remove(&attr);
+        }
+
+        self
+    }
+
+    pub fn is_allowed(&self, tag: &LocalName, attr: &LocalName) -> bool {
+        self.global.contains(attr) || self.per_tag.get(tag).is_some_and(|attrs| attrs.contains(attr))
+    }
+
+    pub(crate) fn clean(&self, tag: &LocalName, attributes: &mut Attributes) {
+        attributes.map.retain(|name, _| self.is_allowed(tag, &name.local));
+    }
+}
+
+impl Default for AttributeAllowlist {
+    fn default() -> Self {
+        let mut allowlist = AttributeAllowlist::new();
+
+        for attr in &GLOBAL {
+            allowlist.allow("*", attr);
+        }
+
+        for (tag, attrs) in &PER_TAG {
+            for attr in attrs.iter() {
+                allowlist.allow(tag, attr);
+            }
+        }
+
+        allowlist
+    }
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+    use html5ever::local_name;
+
+    #[test]
+    fn test_allowlist() {
+        let mut allowlist = AttributeAllowlist::default();
+        assert!(allowlist.is_allowed(&local_name!("a"), &local_name!("href")));
+        assert!(allowlist.is_allowed(&local_name!("p"), &local_name!("dir")));
+        assert!(!allowlist.is_allowed(&local_name!("p"), &local_name!("href")));
+        assert!(!allowlist.is_allowed(&local_name!("a"), &local_name!("onclick")));
+        assert!(!allowlist.is_allowed(&local_name!("div"), &local_name!("class")));
+
+        allowlist.allow("*", "class").disallow("a", "title");
+        assert!(allowlist.is_allowed(&local_name!("div"), &local_name!("class")));
+        assert!(!allowlist.is_allowed(&local_name!("a"), &local_name!("title")));
+
+        allowlist.disallow("*", "lang");
+        assert!(!allowlist.is_allowed(&local_name!("p"), &local_name!("lang")));
+    }
+}
@@ -0,0 +1,326 @@
+use std::iter;
+
+use html5ever::{QualName, local_name, namespace_url, ns};
+use kuchiki::{ElementData, NodeDataRef, NodeRef};
+use kuchiki::iter::{NodeEdge, NodeIterator};
+use lazy_static::lazy_static;
+use log::trace;
+use regex::Regex;
+
+
+lazy_static! {
+    // Gutters with line numbers, e.g. `td.linenos` of Pygments or `td.gutter` of Hexo.
+    static ref GUTTER: Regex = Regex::new(r"(?xi)
+        \b(?:linenos?|lineno|line-numbers?|line-numbers-rows|gutter|ln-num|hljs-ln-numbers
+        |react-syntax-highlighter-line-number)\b
+    ").unwrap();
+
+    // Wrappers of highlighters: Pygments, Rouge, GitHub, Pandoc, Hexo and others.
+    static ref WRAPPER: Regex = Regex::new(r"(?xi)
+        \b(?:highlight|highlighter-rouge|highlighttable|codehilite|sourceCode|syntaxhighlighter
+        |code-block|code-toolbar|prism|hljs|language-[\w+\#-]+|highlight-source-[\w+\#-]+)\b
+    ").unwrap();
+
+    static ref LANGUAGE: Regex = Regex::new(r"(?xi)
+        (?:^|\s)(?:language|lang|highlight-source|brush:)[-:\s]?\s*([a-z][\w+\#-]*)
+    ").unwrap();
+
+    // Pandoc marks code with `sourceCode` followed by the language.
+    static ref PANDOC_LANGUAGE: Regex = Regex::new(r"(?i)\bsourceCode\s+([a-z][\w+\#-]*)").unwrap();
+}
+
+// Highlighted code is flattened only if it has no meaningful markup like links.
+const PRESERVED_TAGS: [&str; 3] = ["a", "img", "iframe"];
+
+
+/// Normalizes highlighted code blocks to `<pre><code class="language-x">`.
+///
+/// Gutters with line numbers are removed and wrappers of highlighters are replaced with the block.
+pub fn normalize(root: &NodeRef) {
+    let gutters = root.inclusive_descendants().elements().filter(is_gutter).collect::<Vec<_>>();
+
+    for gutter in gutters {
+        remove_gutter(&gutter);
+    }
+
+    // Only outer blocks, nested ones are flattened along with them.
+    let blocks = find(root, |elem| elem.name.local == local_name!("pre"))
+        .into_iter()
+        .filter(|pre| !pre.as_node().parent().is_some_and(|parent| is_in_pre(&parent)))
+        .collect::<Vec<_>>();
+
+    for pre in blocks {
+        normalize_block(&pre);
+    }
+}
+
+/// Checks if the node is a part of a code block or inline code, which must be preserved verbatim.
+pub fn is_in_code(node: &NodeRef) -> bool {
+    node.inclusive_ancestors().elements()
+        .any(|elem| matches!(elem.name.local, local_name!("pre") | local_name!("code")))
+}
+
+fn is_in_pre(node: &NodeRef) -> bool {
+    node.inclusive_ancestors().elements().any(|elem| elem.name.local == local_name!("pre"))
+}
+
+fn find<F>(root: &NodeRef, predicate: F) -> Vec<NodeDataRef<ElementData>>
+    where F: Fn(&ElementData) -> bool
+{
+    root.inclusive_descendants().elements().filter(|elem| predicate(elem)).collect()
+}
+
+// Only elements inside highlighted code are gutters, the block itself is never removed,
+// e.g. `pre.line-numbers` of Prism.
+fn is_gutter(elem: &NodeDataRef<ElementData>) -> bool {
+    if matches!(elem.name.local, local_name!("pre") | local_name!("code")) {
+        return false;
+    }
+
+    let is_marked = elem.attributes.borrow().get(local_name!("class")).is_some_and(|class| GUTTER.is_match(class));
+
+    is_marked && elem.as_node().ancestors().elements()
+        .any(|ancestor| ancestor.name.local == local_name!("pre") || has_wrapper_class(&ancestor))
+}
+
+fn remove_gutter(gutter: &NodeDataRef<ElementData>) {
+    let table = if gutter.name.local == local_name!("td") {
+        gutter.as_node().ancestors().elements().find(|elem| elem.name.local == local_name!("table"))
+    } else {
+        None
+    };
+
+    trace!("Removing line numbers");
+    gutter.as_node().detach();
+
+    // A table with a gutter cell and a code cell is replaced with the code.
+    if let Some(table) = table {
+        if let Some(pre) = single_pre(table.as_node()) {
+            trace!("Replacing the table of line numbers with the code");
+            table.as_node().insert_before(pre);
+            table.as_node().detach();
+        }
+    }
+}
+
+// Returns the only block of the table if the table contains nothing else.
+fn single_pre(table: &NodeRef) -> Option<NodeRef> {
+    let mut blocks = table.descendants().elements().filter(|elem| elem.name.local == local_name!("pre"));
+    let pre = blocks.next()?;
+
+    if blocks.next().is_some() {
+        return None;
+    }
+
+    let has_other_content = table.descendants().elements()
+        .any(|elem| PRESERVED_TAGS.contains(&&*elem.name.local) && !is_in_pre(elem.as_node()));
+
+    if has_other_content || table.text_contents().trim() != pre.text_contents().trim() {
+        return None;
+    }
+
+    Some(pre.as_node().clone())
+}
+
+fn normalize_block(pre: &NodeDataRef<ElementData>) {
+    let node = pre.as_node();
+    let language = find_language(node);
+
+    let is_highlighted = language.is_some() || has_wrapper(node) || node.descendants().elements()
+        .any(|elem| elem.attributes.borrow().contains(local_name!("class")));
+
+    let has_markup = node.descendants().elements()
+        .any(|elem| PRESERVED_TAGS.contains(&&*elem.name.local));
+
+    if is_highlighted && !has_markup {
+        let code = NodeRef::new_element(QualName::new(None, ns!(html), local_name!("code")), iter::empty());
+        code.append(NodeRef::new_text(text_of(node)));
+
+        if let Some(ref language) = language {
+            code.as_element().unwrap().attributes.borrow_mut()
+                .insert(local_name!("class"), format!("language-{}", language));
+        }
+
+        let block = NodeRef::new_element(QualName::new(None, ns!(html), local_name!("pre")), iter::empty());
+        block.append(code);
+
+        trace!("Normalizing the code block ({})", language.as_deref().unwrap_or("unknown"));
+
+        let target = outermost_wrapper(node);
+        target.insert_before(block);
+        target.detach();
+    }
+}
+
+fn has_wrapper(node: &NodeRef) -> bool {
+    node.ancestors().take(3).elements().any(|elem| has_wrapper_class(&elem))
+}
+
+fn has_wrapper_class(elem: &ElementData) -> bool {
+    elem.attributes.borrow().get(local_name!("class")).is_some_and(|class| WRAPPER.is_match(class))
+}
+
+// Returns the highest wrapper of the highlighter containing nothing but the block.
+fn outermost_wrapper(pre: &NodeRef) -> NodeRef {
+    let mut target = pre.clone();
+
+    while let Some(parent) = target.parent() {
+        let is_wrapper = parent.as_element().is_some_and(|elem| {
+            matches!(elem.name.local, local_name!("div") | local_name!("figure")) && has_wrapper_class(elem)
+        });
+
+        let is_single = parent.children().all(|child| {
+            child == target || child.as_text().is_some_and(|text| text.borrow().trim().is_empty())
+        });
+
+        if !is_wrapper || !is_single {
+            break;
+        }
+
+        target = parent;
+    }
+
+    target
+}
+
+fn find_language(pre: &NodeRef) -> Option<String> {
+    let candidates = pre.inclusive_descendants().elements()
+        .filter(|elem| elem.name.local == local_name!("code"))
+        .chain(iter::once(pre.clone().into_element_ref().unwrap()))
+        .chain(pre.ancestors().take(3).elements());
+
+    for elem in candidates {
+        let attributes = elem.attributes.borrow();
+
+        if let Some(lang) = attributes.get("data-lang").or_else(|| attributes.get("data-language")) {
+            if !lang.trim().is_empty() {
+                return Some(lang.trim().to_ascii_lowercase());
+            }
+        }
+
+        let class = attributes.get(local_name!("class")).unwrap_or("");
+        let captures = LANGUAGE.captures(class).or_else(|| PANDOC_LANGUAGE.captures(class));
+
+        if let Some(captures) = captures {
+            return Some(captures[1].to_ascii_lowercase());
+        }
+    }
+
+    None
+}
+
+// Lines wrapped into block elements are separated with line breaks.
+fn text_of(pre: &NodeRef) -> String {
+    let mut text = String::new();
+
+    for edge in pre.traverse() {
+        match edge {
+            NodeEdge::Start(node) => {
+                if let Some(data) = node.as_text() {
+                    text.push_str(&data.borrow());
+                } else if node.as_element().is_some_and(|elem| elem.name.local == local_name!("br")) {
+                    text.push('\n');
+                }
+            },
+            NodeEdge::End(node) => {
+                let is_line = node != *pre && node.as_element().is_some_and(|elem| {
+                    matches!(elem.name.local, local_name!("div") | local_name!("p") | local_name!("li"))
+                });
+
+                if is_line && !text.is_empty() && !text.ends_with('\n') {
+                    text.push('\n');
+                }
+            }
+        }
+    }
+
+    text
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+
+    fn normalized(html: &str) -> String {
+        let document = kuchiki::parse_html().one(format!("<div id=\"root\">{}</div>", html));
+        let root = document.select_first("#root").unwrap().as_node().clone();
+        normalize(&root);
+        root.children().map(|child| child.to_string()).collect()
+    }
+
+    #[test]
+    fn test_wrappers() {
+        // Rouge (Jekyll).
+        assert_eq!(
+            normalized("<div class=\"language-rust highlighter-rouge\"><div class=\"highlight\">\
+                <pre class=\"highlight\"><code><span class=\"k\">fn</span> <span class=\"nf\">main</span>() {}\n</code></pre>\
+                </div></div>"),
+            "<pre><code class=\"language-rust\">fn main() {}\n</code></pre>"
+        );
+
+        // GitHub.
+        assert_eq!(
+            normalized("<div class=\"highlight highlight-source-python\"><pre><span class=\"pl-k\">def</span> f():\n    pass</pre></div>"),
+            "<pre><code class=\"language-python\">def f():\n    pass</code></pre>"
+        );
+
+        // Pandoc.
+        assert_eq!(
+            normalized("<div class=\"sourceCode\"><pre class=\"sourceCode haskell\"><code class=\"sourceCode haskell\">\
+                <span id=\"cb1-1\"><span class=\"ot\">x</span> = 1</span>\n</code></pre></div>"),
+            "<pre><code class=\"language-haskell\">x = 1\n</code></pre>"
+        );
+
+        // Lines as blocks.
+        assert_eq!(
+            normalized("<pre class=\"language-js\"><code><div class=\"line\">a();</div><div class=\"line\">b();</div></code></pre>"),
+            "<pre><code class=\"language-js\">a();\nb();\n</code></pre>"
+        );
+    }
+
+    #[test]
+    fn test_gutters() {
+        // Pygments.
+        assert_eq!(
+            normalized("<div class=\"highlight\"><table class=\"highlighttable\"><tr>\
+                <td class=\"linenos\"><div class=\"linenodiv\"><pre>1\n2</pre></div></td>\
+                <td class=\"code\"><div class=\"highlight\"><pre><span class=\"n\">a</span>\n<span class=\"n\">b</span></pre></div></td>\
+                </tr></table></div>"),
+            "<pre><code>a\nb</code></pre>"
+        );
+
+        // Inline line numbers.
+        assert_eq!(
+            normalized("<pre class=\"lang-c\"><code><span class=\"lineno\">1 </span>int x;\n<span class=\"lineno\">2 </span>int y;</code></pre>"),
+            "<pre><code class=\"language-c\">int x;\nint y;</code></pre>"
+        );
+
+        // Prism marks the block itself, only the rows of numbers are removed.
+        assert_eq!(
+            normalized("<pre class=\"line-numbers language-js\"><code>a();<span class=\"line-numbers-rows\"><span></span></span></code></pre>"),
+            "<pre><code class=\"language-js\">a();</code></pre>"
+        );
+
+        // Layout tables are not gutters.
+        let layout = "<table><tbody><tr><td class=\"gutter\"></td><td><article><p>Text</p><pre>code</pre></article></td></tr></tbody></table>";
+        assert_eq!(normalized(layout), layout);
+
+        // Tables are replaced only if the code is all they contain.
+        assert_eq!(
+            normalized("<div class=\"highlight\"><table><tbody><tr><td class=\"gutter\"><pre>1</pre></td>\
+                <td><p>Text</p><pre>code</pre></td></tr></tbody></table></div>"),
+            "<div class=\"highlight\"><table><tbody><tr><td><p>Text</p><pre>code</pre></td></tr></tbody></table></div>"
+        );
+    }
+
+    #[test]
+    fn test_plain_blocks() {
+        let plain = "<pre>  a  <b>b</b>\n\n  c</pre>";
+        assert_eq!(normalized(plain), plain);
+
+        let linked = "<pre class=\"highlight\">see <a href=\"/x\">x</a></pre>";
+        assert_eq!(normalized(linked), linked);
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use html5ever::local_name;
+use kuchiki::{ElementData, NodeDataRef, NodeRef};
+use kuchiki::iter::NodeIterator;
+use lazy_static::lazy_static;
+use log::trace;
+use regex::Regex;
+
+
+lazy_static! {
+    // Ids of definitions: `fn1`, `fn:1` (kramdown), `footnote-1`, `cite_note-1` (MediaWiki), `_ftn1` (Word).
+    static ref DEFINITION_ID: Regex = Regex::new(r"(?xi)
+        ^(?:fn|footnote|endnote|cite_note|_?ftn)(?:[-_:.]?\d|[-_:.]\w)[\w.:-]*$
+    ").unwrap();
+
+    static ref REFERENCE_ID: Regex = Regex::new(r"(?xi)
+        ^(?:fnref|footnote-?ref|endnote-?ref|cite_ref|_?ftnref)
+    ").unwrap();
+
+    static ref SECTION: Regex = Regex::new(r"(?xi)
+        \b(?:footnotes?|endnotes?|references|reflist|notes)\b
+    ").unwrap();
+
+    static ref BACKLINK: Regex = Regex::new(r"(?xi)
+        \b(?:footnote-backref|reversefootnote|mw-cite-backlink|backlink)\b
+    ").unwrap();
+}
+
+// How far sections can be from definitions, e.g. `div.reflist > ol.references > li`.
+const MAX_SECTION_DEPTH: usize = 4;
+
+
+/// A footnote or an endnote of the content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Footnote {
+    /// The `id` of the definition, references link to it.
+    pub id: String,
+    /// The text of the first reference, e.g. `1`, or the number of the footnote.
+    pub label: String,
+    /// The text of the definition without backlinks.
+    pub text: String,
+    /// The inner HTML of the definition.
+    pub html: String,
+}
+
+
+/// Checks if the `id` belongs to a footnote or a reference to it, so it must be kept.
+pub fn is_footnote_id(id: &str) -> bool {
+    DEFINITION_ID.is_match(id) || REFERENCE_ID.is_match(id)
+}
+
+/// Finds sections containing definitions of footnotes.
+pub fn find_sections(root: &NodeRef) -> Vec<NodeRef> {
+    let mut sections: Vec<NodeRef> = Vec::new();
+
+    for definition in find_definitions(root) {
+        let section = find_section(definition.as_node());
+
+        if !sections.iter().any(|other| section.inclusive_ancestors().any(|node| node == *other)) {
+            sections.retain(|other| !other.ancestors().any(|node| node == section));
+            sections.push(section);
+        }
+    }
+
+    sections
+}
+
+/// Returns the section and its definitions along with elements between them.
+///
+/// Contents of definitions are not included, so they are cleaned as usual.
+pub fn skeleton(section: &NodeRef) -> Vec<NodeRef> {
+    let mut nodes = vec![section.clone()];
+
+    for definition in find_definitions(section) {
+        for node in definition.as_node().inclusive_ancestors() {
+            if node == *section {
+                break;
+            }
+
+            if !nodes.contains(&node) {
+                nodes.push(node);
+            }
+        }
+    }
+
+    nodes
+}
+
+/// Appends sections missing in the content if the content refers to them.
+///
+/// Returns appended sections.
+pub fn append_missing(content: &NodeRef, sections: &[NodeRef]) -> Vec<NodeRef> {
+    let mut appended = Vec::new();
+
+    let targets = content.inclusive_descendants().elements()
+        .filter(|elem| elem.name.local == local_name!("a"))
+        .filter_map(|elem| elem.attributes.borrow().get(local_name!("href"))?.strip_prefix('#').map(String::from))
+        .collect::<Vec<_>>();
+
+    for section in sections {
+        if section.inclusive_ancestors().any(|node| node == *content) {
+            continue;
+        }
+
+        let is_referred = find_definitions(section).iter()
+            .filter_map(|definition| definition.attributes.borrow().get(local_name!("id")).map(String::from))
+            .any(|id| targets.contains(&id));
+
+        if is_referred {
+            trace!("Appending the section of footnotes");
+            section.detach();
+            content.append(section.clone());
+            appended.push(section.clone());
+        }
+    }
+
+    appended
+}
+
+/// Collects footnotes defined in the content.
+pub fn collect(content: &NodeRef) -> Vec<Footnote> {
+    let mut labels = HashMap::new();
+
+    for elem in content.inclusive_descendants().elements() {
+        if elem.name.local != local_name!("a") {
+            continue;
+        }
+
+        let attributes = elem.attributes.borrow();
+
+        if let Some(target) = attributes.get(local_name!("href")).and_then(|href| href.strip_prefix('#')) {
+            let label = elem.text_contents().trim().trim_matches(&['[', ']', '(', ')'][..]).trim().to_string();
+
+            if !label.is_empty() {
+                labels.entry(target.to_string()).or_insert(label);
+            }
+        }
+    }
+
+    find_definitions(content).iter().enumerate()
+        .filter_map(|(index, definition)| {
+            let id = definition.attributes.borrow().get(local_name!("id"))?.to_string();
+
+            let mut text = String::new();
+            text_without_backlinks(definition.as_node(), &mut text);
+
+            Some(Footnote {
+                label: labels.get(&id).cloned().unwrap_or_else(|| (index + 1).to_string()),
+                text: text.split_whitespace().collect::<Vec<_>>().join(" "),
+                html: definition.as_node().children().map(|child| child.to_string()).collect(),
+                id,
+            })
+        })
+        .collect()
+}
+
+fn find_definitions(root: &NodeRef) -> Vec<NodeDataRef<ElementData>> {
+    root.inclusive_descendants().elements().filter(|elem| is_definition(elem)).collect()
+}
+
+fn is_definition(elem: &ElementData) -> bool {
+    let attributes = elem.attributes.borrow();
+
+    if matches!(attributes.get(local_name!("role")), Some("doc-footnote") | Some("doc-endnote")) {
+        return true;
+    }
+
+    // References are usually inline elements, definitions are blocks.
+    !matches!(elem.name.local, local_name!("a") | local_name!("sup") | local_name!("span")) &&
+        attributes.get(local_name!("id")).is_some_and(|id| DEFINITION_ID.is_match(id) && !REFERENCE_ID.is_match(id))
+}
+
+// Returns the outermost container of footnotes or the list of definitions.
+fn find_section(definition: &NodeRef) -> NodeRef {
+    let section = definition.ancestors().elements().take(MAX_SECTION_DEPTH)
+        .filter(|elem| {
+            let attributes = elem.attributes.borrow();
+
+            attributes.get(local_name!("role")) == Some("doc-endnotes") ||
+                attributes.get(local_name!("class")).is_some_and(|class| SECTION.is_match(class)) ||
+                attributes.get(local_name!("id")).is_some_and(|id| SECTION.is_match(id))
+        })
+        .last();
+
+    if let Some(section) = section {
+        return section.as_node().clone();
+    }
+
+    match definition.parent() {
+        Some(parent) if parent.as_element().is_some_and(|elem| {
+            matches!(elem.name.local, local_name!("ol") | local_name!("ul"))
+        }) => parent,
+        _ => definition.clone()
+    }
+}
+
+fn text_without_backlinks(node: &NodeRef, text: &mut String) {
+    for child in node.children() {
+        if let Some(data) = child.as_text() {
+            text.push_str(&data.borrow());
+        } else if let Some(elem) = child.as_element() {
+            if !is_backlink(elem) {
+                text_without_backlinks(&child, text);
+            }
+
+            // Paragraphs of the note must not stick together.
+            if matches!(elem.name.local, local_name!("p") | local_name!("div") | local_name!("br")) {
+                text.push(' ');
+            }
+        }
+    }
+}
+
+fn is_backlink(elem: &ElementData) -> bool {
+    let attributes = elem.attributes.borrow();
+
+    attributes.get(local_name!("role")) == Some("doc-backlink") ||
+        attributes.get(local_name!("class")).is_some_and(|class| BACKLINK.is_match(class)) ||
+        attributes.get(local_name!("href"))
+            .and_then(|href| href.strip_prefix('#'))
+            .is_some_and(|target| REFERENCE_ID.is_match(target))
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+
+    #[test]
+    fn test_ids() {
+        for id in ["fn1", "fn:1", "fn-note", "footnote-2", "cite_note-Smith-3", "_ftn4", "fnref1", "cite_ref-1"] {
+            assert!(is_footnote_id(id), "{}", id);
+        }
+
+        for id in ["footnotes", "fnord", "function", "content", "references"] {
+            assert!(!is_footnote_id(id), "{}", id);
+        }
+    }
+
+    #[test]
+    fn test_collect() {
+        let root = kuchiki::parse_html().one("<div>
+            <p>Text<sup id=\"fnref:1\"><a href=\"#fn:1\">[1]</a></sup> and more<sup><a href=\"#fn:2\">2</a></sup>.</p>
+            <div class=\"footnotes\" role=\"doc-endnotes\"><ol>
+                <li id=\"fn:1\" role=\"doc-endnote\"><p>First <em>note</em>. <a href=\"#fnref:1\" class=\"reversefootnote\">↩</a></p></li>
+                <li id=\"fn:2\"><p>Second note.</p></li>
+            </ol></div>
+        </div>");
+
+        let sections = find_sections(&root);
+        assert_eq!(sections.len(), 1);
+        assert_eq!(sections[0].as_element().unwrap().attributes.borrow().get("class"), Some("footnotes"));
+
+        let footnotes = collect(&root);
+        assert_eq!(footnotes.len(), 2);
+        assert_eq!((&footnotes[0].id[..], &footnotes[0].label[..]), ("fn:1", "1"));
+        assert_eq!(footnotes[0].text, "First note.");
+        assert!(footnotes[0].html.contains("<em>note</em>"));
+        assert_eq!((&footnotes[1].label[..], &footnotes[1].text[..]), ("2", "Second note."));
+    }
+}
@@ -0,0 +1,156 @@
+use lazy_static::lazy_static;
+use regex::Regex;
+
+
+const UNLIKELY_CANDIDATE: [&str; 32] = [
+    "-ad-", "ai2html", "banner", "breadcrumbs", "combx", "comment", "community", "cover-wrap",
+    "disqus", "extra", "footer", "gdpr", "header", "legends", "menu", "modal", "related", "remark",
+    "replies", "rss", "shoutbox", "sidebar", "skyscraper", "social", "sponsor", "supplemental",
+    "ad-break", "agegate", "pagination", "pager", "popup", "yom-remote",
+];
+const MAYBE_CANDIDATE: [&str; 6] = [
+    "and", "article", "body", "column", "main", "shadow",
+];
+const POSITIVE: [&str; 13] = [
+    "article", "body", "content", "entry", "hentry", "h-entry", "main", "page", "pagination",
+    "post", "text", "blog", "story",
+];
+const NEGATIVE: [&str; 32] = [
+    "-ad-", "hidden", r"^hid$", r"\shid$", r"\shid\s", r"^hid\s", "banner", "combx", "comment",
+    "com-", "contact", "foot", "footer", "footnote", "gdpr", "masthead", "media", "meta", "modal",
+    "outbrain", "promo", "related", "scroll", "share", "shoutbox", "sidebar", "skyscraper",
+    "sponsor", "shopping", "tags", "tool", "widget",
+];
+const VIDEO: [&str; 1] = [
+    r"//(www\.)?(dailymotion|youtube|youtube-nocookie|player\.vimeo)\.com",
+];
+
+
+/// A list of patterns used to classify elements by their `class`, `id` or URLs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Heuristic {
+    /// Elements that are removed as unlikely to be a part of the content.
+    UnlikelyCandidate,
+    /// Elements that are never removed as unlikely candidates.
+    MaybeCandidate,
+    /// Elements that get a bonus to the score.
+    Positive,
+    /// Elements that get a penalty to the score.
+    Negative,
+    /// URLs of media embeds that are kept, media of the site itself is always kept.
+    Video,
+}
+
+
+#[derive(Clone)]
+pub struct PatternList {
+    patterns: Vec<String>,
+    regex: Regex,
+}
+
+impl PatternList {
+    fn new(patterns: &[&str]) -> PatternList {
+        let patterns = patterns.iter().map(|&p| p.to_string()).collect::<Vec<_>>();
+        let regex = compile(&patterns);
+
+        PatternList { patterns, regex }
+    }
+
+    pub fn extend<I, S>(&mut self, patterns: I)
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        self.patterns.extend(patterns.into_iter().map(Into::into));
+        self.regex = compile(&self.patterns);
+    }
+
+    pub fn replace<I, S>(&mut self, patterns: I)
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        self.patterns.clear();
+        self.extend(patterns);
+    }
+
+    /// Replaces patterns with the compiled regex, keeping its flags.
+    pub fn replace_with_regex(&mut self, regex: Regex) {
+        self.patterns = vec![regex.as_str().to_string()];
+        self.regex = regex;
+    }
+
+    #[inline]
+    pub fn is_match(&self, text: &str) -> bool {
+        self.regex.is_match(text)
+    }
+}
+
+fn compile(patterns: &[String]) -> Regex {
+    // An empty alternation matches everything, so use a pattern that never matches.
+    if patterns.is_empty() {
+        return Regex::new(r"[^\s\S]").unwrap();
+    }
+
+    Regex::new(&format!("(?i){}", patterns.join("|"))).expect("invalid heuristic pattern")
+}
+
+
+#[derive(Clone)]
+pub struct Heuristics {
+    pub unlikely_candidate: PatternList,
+    pub maybe_candidate: PatternList,
+    pub positive: PatternList,
+    pub negative: PatternList,
+    pub video: PatternList,
+}
+
+lazy_static! {
+    static ref DEFAULT: Heuristics = Heuristics {
+        unlikely_candidate: PatternList::new(&UNLIKELY_CANDIDATE),
+        maybe_candidate: PatternList::new(&MAYBE_CANDIDATE),
+        positive: PatternList::new(&POSITIVE),
+        negative: PatternList::new(&NEGATIVE),
+        video: PatternList::new(&VIDEO),
+    };
+}
+
+impl Default for Heuristics {
+    fn default() -> Self {
+        DEFAULT.clone()
+    }
+}
+
+impl Heuristics {
+    pub fn get_mut(&mut self, heuristic: Heuristic) -> &mut PatternList {
+        match heuristic {
+            Heuristic::UnlikelyCandidate => &mut self.unlikely_candidate,
+            Heuristic::MaybeCandidate => &mut self.maybe_candidate,
+            Heuristic::Positive => &mut self.positive,
+            Heuristic::Negative => &mut self.negative,
+            Heuristic::Video => &mut self.video,
+        }
+    }
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+
+    #[test]
+    fn test_pattern_list() {
+        let mut heuristics = Heuristics::default();
+        assert!(heuristics.negative.is_match("hid"));
+        assert!(heuristics.negative.is_match("Sidebar"));
+        assert!(!heuristics.negative.is_match("paywall-teaser"));
+
+        heuristics.get_mut(Heuristic::Negative).extend(["paywall-teaser"]);
+        assert!(heuristics.negative.is_match("paywall-teaser"));
+        assert!(heuristics.negative.is_match("sidebar"));
+
+        heuristics.get_mut(Heuristic::Negative).replace(["paywall"]);
+        assert!(heuristics.negative.is_match("paywall-teaser"));
+        assert!(!heuristics.negative.is_match("sidebar"));
+
+        heuristics.get_mut(Heuristic::Negative).replace(Vec::<String>::new());
+        assert!(!heuristics.negative.is_match("paywall"));
+        assert!(!heuristics.negative.is_match(""));
+    }
+}
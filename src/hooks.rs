@@ -0,0 +1,56 @@
+use html5ever::LocalName;
+use kuchiki::{ElementData, NodeDataRef, NodeRef};
+
+use crate::{Metadata, NodeInfo};
+
+
+/// What to do with an element during the capturing stage.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CaptureAction {
+    /// Apply the default heuristics.
+    Continue,
+    /// Remove the element with its subtree.
+    Remove,
+    /// Rename the element and apply the default heuristics to it.
+    Rename(LocalName),
+}
+
+/// What to do with an element during the bubbling stage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BubbleAction {
+    /// Apply the default heuristics.
+    Continue,
+    /// Remove the element with its subtree.
+    Remove,
+    /// Keep the element regardless of the cleaning heuristics.
+    Keep,
+}
+
+/// Custom stages of the extraction pipeline.
+///
+/// All methods do nothing by default, so implement only the needed ones.
+/// Hooks are called in the order they were registered.
+pub trait ExtractionHook {
+    /// Called with the raw HTML, returns a replacement if needed.
+    fn before_parse(&mut self, _html: &str) -> Option<String> {
+        None
+    }
+
+    /// Called for every element before its children are visited.
+    fn on_capture(&mut self, _elem: &NodeDataRef<ElementData>) -> CaptureAction {
+        CaptureAction::Continue
+    }
+
+    /// Called for every element after its children are visited and scored.
+    fn on_bubble(&mut self, _elem: &NodeDataRef<ElementData>, _info: &NodeInfo) -> BubbleAction {
+        BubbleAction::Continue
+    }
+
+    /// Called for every candidate with its final score, returns the adjusted score.
+    fn adjust_score(&mut self, _candidate: &NodeDataRef<ElementData>, _info: &NodeInfo, score: f32) -> f32 {
+        score
+    }
+
+    /// Called with the extracted content.
+    fn post_process(&mut self, _content: &NodeRef, _metadata: &mut Metadata) {}
+}
@@ -0,0 +1,164 @@
+use html5ever::local_name;
+use kuchiki::{ElementData, NodeDataRef, NodeRef};
+use kuchiki::iter::NodeIterator;
+use url::Url;
+
+
+/// A link of the content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Link {
+    /// The resolved URL.
+    pub url: Url,
+    /// The anchor text with collapsed whitespaces.
+    pub text: String,
+    /// Lowercased tokens of `rel`, e.g. `nofollow`.
+    pub rel: Vec<String>,
+    /// The link leads to the same host as the base URL, `www.` is ignored.
+    pub is_internal: bool,
+}
+
+/// An image of the content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Image {
+    /// The resolved URL, the first candidate of `srcset` if there is no `src`.
+    pub src: Url,
+    pub alt: Option<String>,
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    /// The caption of the enclosing `<figure>`.
+    pub caption: Option<String>,
+}
+
+
+/// Collects links to web pages, in-page anchors and other schemes like `mailto:` are skipped.
+pub fn collect_links(content: &NodeRef, base_url: Option<&Url>) -> Vec<Link> {
+    let base_host = base_url.and_then(|url| url.host_str()).map(strip_www);
+
+    content.inclusive_descendants().elements()
+        .filter(|elem| elem.name.local == local_name!("a"))
+        .filter_map(|elem| {
+            let attributes = elem.attributes.borrow();
+            let href = attributes.get(local_name!("href"))?;
+
+            if href.trim_start().starts_with('#') {
+                return None;
+            }
+
+            let url = resolve(href, base_url)?;
+            let is_internal = url.host_str().map(strip_www) == base_host && base_host.is_some();
+
+            Some(Link {
+                text: normalize_spaces(&elem.text_contents()),
+                rel: attributes.get(local_name!("rel")).unwrap_or("")
+                    .split_whitespace()
+                    .map(str::to_ascii_lowercase)
+                    .collect(),
+                is_internal,
+                url,
+            })
+        })
+        .collect()
+}
+
+/// Collects images with web URLs, inline `data:` ones are skipped.
+pub fn collect_images(content: &NodeRef, base_url: Option<&Url>) -> Vec<Image> {
+    content.inclusive_descendants().elements()
+        .filter(|elem| elem.name.local == local_name!("img"))
+        .filter_map(|elem| {
+            let attributes = elem.attributes.borrow();
+
+            let src = attributes.get(local_name!("src"))
+                .filter(|src| !src.trim().is_empty())
+                .or_else(|| attributes.get(local_name!("srcset"))?.split_whitespace().next())?;
+
+            Some(Image {
+                src: resolve(src, base_url)?,
+                alt: attributes.get(local_name!("alt")).map(normalize_spaces),
+                width: attributes.get(local_name!("width")).and_then(parse_dimension),
+                height: attributes.get(local_name!("height")).and_then(parse_dimension),
+                caption: find_caption(&elem),
+            })
+        })
+        .collect()
+}
+
+fn resolve(url: &str, base_url: Option<&Url>) -> Option<Url> {
+    let url = match base_url {
+        Some(base_url) => base_url.join(url.trim()),
+        None => Url::parse(url.trim())
+    };
+
+    url.ok().filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+fn find_caption(img: &NodeDataRef<ElementData>) -> Option<String> {
+    let figure = img.as_node().ancestors().elements()
+        .find(|elem| elem.name.local == local_name!("figure"))?;
+
+    let caption = figure.as_node().descendants().elements()
+        .find(|elem| elem.name.local == local_name!("figcaption"))?;
+
+    Some(normalize_spaces(&caption.text_contents())).filter(|caption| !caption.is_empty())
+}
+
+// Accepts values like `640` and `640px`, but not relative ones like `50%`.
+fn parse_dimension(value: &str) -> Option<u32> {
+    let value = value.trim();
+    let value = value.strip_suffix("px").unwrap_or(value);
+    value.trim().parse().ok()
+}
+
+fn strip_www(host: &str) -> &str {
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+fn normalize_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+
+    #[test]
+    fn test_collect() {
+        let root = kuchiki::parse_html().one("<div>
+            <a href=\"/about\">About  us</a>
+            <a href=\"https://www.example.com/x\" rel=\"NoFollow ugc\">X</a>
+            <a href=\"https://other.org/\">Other</a>
+            <a href=\"#note\">1</a>
+            <a href=\"mailto:me@example.com\">Mail</a>
+            <figure><img src=\"a.png\" alt=\"A\" width=\"640px\" height=\"50%\"><figcaption> The A </figcaption></figure>
+            <img srcset=\"b.png 1x, b@2x.png 2x\">
+            <img src=\"data:image/png;base64,AA==\">
+        </div>");
+
+        let base_url = Url::parse("https://example.com/post").unwrap();
+        let links = collect_links(&root, Some(&base_url));
+
+        let summary = links.iter()
+            .map(|link| (link.url.as_str(), &link.text[..], link.is_internal))
+            .collect::<Vec<_>>();
+
+        assert_eq!(summary, [
+            ("https://example.com/about", "About us", true),
+            ("https://www.example.com/x", "X", true),
+            ("https://other.org/", "Other", false),
+        ]);
+        assert_eq!(links[1].rel, ["nofollow", "ugc"]);
+
+        let images = collect_images(&root, Some(&base_url));
+        assert_eq!(images.len(), 2);
+        assert_eq!(images[0], Image {
+            src: Url::parse("https://example.com/a.png").unwrap(),
+            alt: Some("A".into()),
+            width: Some(640),
+            height: None,
+            caption: Some("The A".into()),
+        });
+        assert_eq!(images[1].src.as_str(), "https://example.com/b.png");
+        assert_eq!(images[1].caption, None);
+    }
+}
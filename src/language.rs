@@ -0,0 +1,127 @@
+/// A language detected from the text.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DetectedLanguage {
+    /// BCP 47 tag, e.g. `en`.
+    pub tag: String,
+    /// Confidence in the range from 0 to 1.
+    pub confidence: f32,
+}
+
+// Detections with lower confidence don't override the declared language.
+const MIN_CONFIDENCE: f32 = 0.5;
+
+
+/// Identifies the language of the text using trigrams.
+#[cfg(feature = "language-detection")]
+pub fn detect(text: &str) -> Option<DetectedLanguage> {
+    let info = whatlang::detect(text)?;
+
+    Some(DetectedLanguage {
+        tag: to_bcp47(info.lang().code()).to_string(),
+        confidence: info.confidence() as f32,
+    })
+}
+
+#[cfg(not(feature = "language-detection"))]
+pub fn detect(_text: &str) -> Option<DetectedLanguage> {
+    None
+}
+
+/// Chooses between the declared language and the detected one.
+///
+/// The declared tag is preferred if it agrees with the detection, because it can have a region.
+pub fn best_guess(declared: Option<&str>, detected: Option<&DetectedLanguage>) -> Option<String> {
+    let detected = detected.filter(|detected| detected.confidence >= MIN_CONFIDENCE);
+
+    match (declared, detected) {
+        (Some(declared), Some(detected)) if primary_subtag(declared) != detected.tag => {
+            Some(detected.tag.clone())
+        },
+        (Some(declared), _) => Some(declared.to_string()),
+        (None, Some(detected)) => Some(detected.tag.clone()),
+        (None, None) => None
+    }
+}
+
+/// Cleans up the value of `lang` or `Content-Language`.
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    // `Content-Language` can contain several languages.
+    let tag = tag.split(',').next().unwrap().trim().replace('_', "-");
+
+    if tag.is_empty() || !tag.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') {
+        return None;
+    }
+
+    Some(tag)
+}
+
+fn primary_subtag(tag: &str) -> String {
+    let primary = tag.split('-').next().unwrap().to_ascii_lowercase();
+
+    // Old tags are still widely used.
+    match &primary[..] {
+        "iw" => "he".to_string(),
+        "in" => "id".to_string(),
+        "ji" => "yi".to_string(),
+        _ => primary
+    }
+}
+
+// Converts ISO 639-3 codes to the shortest BCP 47 tags.
+#[cfg(feature = "language-detection")]
+fn to_bcp47(code: &str) -> &str {
+    match code {
+        "afr" => "af", "aka" => "ak", "amh" => "am", "ara" => "ar", "aze" => "az",
+        "bel" => "be", "ben" => "bn", "bul" => "bg", "cat" => "ca", "ces" => "cs",
+        "cmn" => "zh", "dan" => "da", "deu" => "de", "ell" => "el", "eng" => "en",
+        "epo" => "eo", "est" => "et", "fin" => "fi", "fra" => "fr", "guj" => "gu",
+        "heb" => "he", "hin" => "hi", "hrv" => "hr", "hun" => "hu", "hye" => "hy",
+        "ind" => "id", "ita" => "it", "jav" => "jv", "jpn" => "ja", "kan" => "kn",
+        "kat" => "ka", "khm" => "km", "kor" => "ko", "lat" => "la", "lav" => "lv",
+        "lit" => "lt", "mal" => "ml", "mar" => "mr", "mkd" => "mk", "mya" => "my",
+        "nep" => "ne", "nld" => "nl", "nob" => "nb", "ori" => "or", "pan" => "pa",
+        "pes" => "fa", "pol" => "pl", "por" => "pt", "ron" => "ro", "rus" => "ru",
+        "sin" => "si", "slk" => "sk", "slv" => "sl", "sna" => "sn", "spa" => "es",
+        "srp" => "sr", "swe" => "sv", "tam" => "ta", "tel" => "te", "tgl" => "tl",
+        "tha" => "th", "tuk" => "tk", "tur" => "tr", "ukr" => "uk", "urd" => "ur",
+        "uzb" => "uz", "vie" => "vi", "yid" => "yi", "zul" => "zu",
+        _ => code
+    }
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+
+    #[test]
+    fn test_best_guess() {
+        let detected = |tag: &str, confidence| DetectedLanguage { tag: tag.into(), confidence };
+
+        assert_eq!(best_guess(Some("en-GB"), Some(&detected("en", 0.9))).as_deref(), Some("en-GB"));
+        assert_eq!(best_guess(Some("en"), Some(&detected("de", 0.9))).as_deref(), Some("de"));
+        assert_eq!(best_guess(Some("en"), Some(&detected("de", 0.1))).as_deref(), Some("en"));
+        assert_eq!(best_guess(Some("iw"), Some(&detected("he", 0.9))).as_deref(), Some("iw"));
+        assert_eq!(best_guess(None, Some(&detected("de", 0.9))).as_deref(), Some("de"));
+        assert_eq!(best_guess(None, None), None);
+    }
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag(" en_US ").as_deref(), Some("en-US"));
+        assert_eq!(normalize_tag("de, en").as_deref(), Some("de"));
+        assert_eq!(normalize_tag(""), None);
+        assert_eq!(normalize_tag("{{lang}}"), None);
+    }
+
+    #[test]
+    #[cfg(feature = "language-detection")]
+    fn test_detect() {
+        let text = "Der schnelle braune Fuchs springt über den faulen Hund, \
+                    während die Katze auf dem Dach in der Sonne schläft.";
+
+        let detected = detect(text).unwrap();
+        assert_eq!(detected.tag, "de");
+        assert!(detected.confidence > 0.5);
+    }
+}
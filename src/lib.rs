@@ -1,33 +1,68 @@
+use std::borrow::Cow;
 use std::cmp;
+use std::collections::HashSet;
 use std::iter;
+use std::sync::Arc;
 use std::f32;
 use std::fmt;
 
 use regex::Regex;
-use html5ever::{QualName, local_name, namespace_url, ns};
-use kuchiki::{NodeRef, NodeDataRef, NodeData, ElementData, Attributes};
+use html5ever::{LocalName, QualName, local_name, namespace_url, ns};
+use kuchiki::{NodeRef, NodeDataRef, NodeData, ElementData, Attributes, Selectors};
 use kuchiki::traits::TendrilSink;
 use kuchiki::iter::NodeIterator;
 use lazy_static::lazy_static;
 use log::trace;
+use unicode_segmentation::UnicodeSegmentation;
 use url::Url;
 
+pub use attributes::AttributeAllowlist;
+pub use heuristics::Heuristic;
+pub use footnotes::Footnote;
+pub use hooks::{BubbleAction, CaptureAction, ExtractionHook};
+pub use inventory::{Image, Link};
+pub use language::DetectedLanguage;
+pub use links::LinkNormalizer;
 pub use metadata::Metadata;
+pub use scoring::ScoringParams;
+pub use site_rules::{SiteRule, SiteRuleError, SiteRules};
+pub use toc::TocEntry;
+use heuristics::{Heuristics, PatternList};
 use node_cache::NodeCache;
 
+mod attributes;
+mod code;
+mod footnotes;
+mod heuristics;
+mod hooks;
+mod inventory;
+mod language;
+mod links;
+mod math;
 mod metadata;
 mod node_cache;
+mod pagination;
+mod sanitize;
+mod scoring;
+mod site_rules;
+mod stats;
+mod toc;
 
 // TODO: add examples.
 // TODO: document it!
 
 type ElemRef = NodeDataRef<ElementData>;
 
+const STRIPPED_TAGS: [&str; 11] = [
+    "object", "embed", "iframe", "footer", "aside", "link",
+    "input", "textarea", "button", "select", "svg",
+];
+
 trait NodeRefExt {
     fn node_ref(&self) -> &NodeRef;
 
     fn is(&self, name: QualName) -> bool {
-        self.node_ref().as_element().map_or(false, |e| e.name == name)
+        self.node_ref().as_element().is_some_and(|e| e.name == name)
     }
 
     fn replace<N: NodeRefExt>(&self, node: &N) {
@@ -84,33 +119,13 @@ impl NodeRefExt for ElemRef {
 }
 
 lazy_static! {
-    static ref UNLIKELY_CANDIDATE: Regex = Regex::new(r"(?xi)
-        -ad-|ai2html|banner|breadcrumbs|combx|comment|community|cover-wrap|disqus|extra|footer|gdpr|header|legends|menu|
-        modal|related|remark|replies|rss|shoutbox|sidebar|skyscraper|social|sponsor|supplemental|
-        ad-break|agegate|pagination|pager|popup|yom-remote
-    ").unwrap();
-
-    static ref MAYBE_CANDIDATE: Regex = Regex::new(r"(?xi)
-        and|article|body|column|main|shadow
-    ").unwrap();
-
-    static ref POSITIVE: Regex = Regex::new(r"(?xi)
-        article|body|content|entry|hentry|h-entry|main|page|pagination|post|text|blog|story
-    ").unwrap();
-
-    static ref NEGATIVE: Regex = Regex::new(r"(?xi)
-        -ad-|hidden|^hid$|\shid$|\shid\s|^hid\s|banner|combx|comment|com-|contact|foot|footer|footnote|
-        gdpr|masthead|media|meta|modal|outbrain|promo|related|scroll|share|shoutbox|sidebar|skyscraper|
-        sponsor|shopping|tags|tool|widget
-    ").unwrap();
-
     // TODO: restore byline parsing.
     //static ref BYLINE: Regex = Regex::new(r"(?xi)
         //byline|author|dateline|writtenby|p-author
     //").unwrap();
 
-    static ref VIDEO: Regex = Regex::new(r"(?xi)
-        //(www\.)?(dailymotion|youtube|youtube-nocookie|player\.vimeo)\.com
+    static ref FIGURE: Regex = Regex::new(r"(?xi)
+        figure|wp-caption|image-wrap|img-wrap|photo|picture
     ").unwrap();
 
     static ref PROTOCOL: Regex = Regex::new(r"^\w+:").unwrap();
@@ -157,7 +172,7 @@ fn extract_byline(elem: &ElemRef) -> Option<String> {
     }
 }
 
-fn is_unlikely_candidate(elem: &ElemRef) -> bool {
+fn is_unlikely_candidate(elem: &ElemRef, heuristics: &Heuristics) -> bool {
     match elem.name {
         tag!("a") | tag!("body") => return false,
         _ => {}
@@ -169,11 +184,163 @@ fn is_unlikely_candidate(elem: &ElemRef) -> bool {
     let classes = attributes.get(attrib!("class")).unwrap_or("");
     let id = attributes.get(attrib!("id")).unwrap_or("");
 
-    (UNLIKELY_CANDIDATE.is_match(classes) || UNLIKELY_CANDIDATE.is_match(id)) &&
-        !(MAYBE_CANDIDATE.is_match(classes) || MAYBE_CANDIDATE.is_match(id))
+    let unlikely = &heuristics.unlikely_candidate;
+    let maybe = &heuristics.maybe_candidate;
+
+    (unlikely.is_match(classes) || unlikely.is_match(id)) &&
+        !(maybe.is_match(classes) || maybe.is_match(id))
 }
 
-fn transform_div(div: &ElemRef) {
+fn is_figure(elem: &ElemRef, info: &NodeInfo) -> bool {
+    match elem.name {
+        tag!("figure") => true,
+        tag!("div") => info.img_count > 0 && has_figure_class(elem),
+        _ => false
+    }
+}
+
+fn has_figure_class(elem: &ElemRef) -> bool {
+    let attributes = elem.attributes.borrow();
+
+    let classes = attributes.get(attrib!("class")).unwrap_or("");
+    let id = attributes.get(attrib!("id")).unwrap_or("");
+
+    FIGURE.is_match(classes) || FIGURE.is_match(id)
+}
+
+// Like `is_figure`, but during capturing, when images aren't counted yet.
+fn has_figure_ancestor(node: &NodeRef) -> bool {
+    node.ancestors().elements().any(|elem| {
+        elem.is(tag!("figure")) || elem.is(tag!("div")) && has_figure_class(&elem) &&
+            elem.as_node().descendants().any(|node| node.is(tag!("img")))
+    })
+}
+
+fn is_data_table(table: &ElemRef) -> bool {
+    debug_assert_eq!(table.name, tag!("table"));
+
+    {
+        let attributes = table.attributes.borrow();
+
+        if attributes.get(attrib!("role")) == Some("presentation") {
+            return false;
+        }
+
+        if attributes.get("datatable") == Some("0") {
+            return false;
+        }
+
+        if attributes.contains(attrib!("summary")) {
+            return true;
+        }
+    }
+
+    let node = table.as_node();
+
+    let has_caption = node.children().elements()
+        .any(|child| child.is(tag!("caption")) && child.as_node().first_child().is_some());
+
+    if has_caption {
+        return true;
+    }
+
+    // Nested tables indicate a layout table.
+    if node.descendants().elements().any(|elem| elem.is(tag!("table"))) {
+        return false;
+    }
+
+    let has_data_elem = node.descendants().elements().any(|elem| matches!{
+        elem.name,
+        tag!("col") | tag!("colgroup") | tag!("tfoot") | tag!("thead") | tag!("th")
+    });
+
+    if has_data_elem {
+        return true;
+    }
+
+    let (rows, columns) = table_size(node);
+
+    rows >= 10 || columns > 4 || rows * columns > 10
+}
+
+fn table_rows(table: &NodeRef) -> Vec<ElemRef> {
+    let mut rows = Vec::new();
+
+    for child in table.children().elements() {
+        match child.name {
+            tag!("thead") | tag!("tbody") | tag!("tfoot") => {
+                rows.extend(child.as_node().children().elements().filter(|e| e.is(tag!("tr"))));
+            },
+            tag!("tr") => rows.push(child),
+            _ => {}
+        }
+    }
+
+    rows
+}
+
+fn table_cells(row: &ElemRef) -> impl Iterator<Item = ElemRef> {
+    row.as_node().children().elements()
+        .filter(|elem| elem.is(tag!("td")) || elem.is(tag!("th")))
+}
+
+fn table_size(table: &NodeRef) -> (u32, u32) {
+    let mut rows = 0;
+    let mut columns = 0;
+
+    for row in table_rows(table) {
+        rows += 1;
+
+        let row_columns = table_cells(&row)
+            .map(|cell| {
+                let attributes = cell.attributes.borrow();
+                attributes.get(attrib!("colspan")).and_then(|span| span.parse().ok()).unwrap_or(1)
+            })
+            .sum();
+
+        columns = cmp::max(columns, row_columns);
+    }
+
+    (rows, columns)
+}
+
+// Returns pairs of cells and elements replacing them.
+fn unwrap_layout_table(table: &ElemRef) -> Vec<(NodeRef, NodeRef)> {
+    let node = table.as_node();
+    let mut cells = table_rows(node).iter().flat_map(table_cells).collect::<Vec<_>>();
+
+    let unwrap_cell = |cell: &ElemRef| {
+        let is_phrasing = !cell.as_node().descendants().elements().any(|elem| matches!{
+            elem.name,
+            tag!("blockquote") | tag!("dl") | tag!("div") | tag!("ol") | tag!("p") |
+            tag!("pre") | tag!("table") | tag!("ul") | tag!("select")
+        });
+
+        (cell.as_node().clone(), cell.rename(if is_phrasing { tag!("p") } else { tag!("div") }))
+    };
+
+    // Single-cell tables are replaced by the cell itself.
+    if cells.len() == 1 {
+        let renamed = unwrap_cell(&cells.pop().unwrap());
+        node.replace(&renamed.1);
+        return vec![renamed];
+    }
+
+    let div = NodeRef::new_element(tag!("div"), iter::empty());
+    let mut renamed = Vec::with_capacity(cells.len());
+
+    for cell in cells {
+        let pair = unwrap_cell(&cell);
+        div.append(pair.1.clone());
+        renamed.push(pair);
+    }
+
+    node.replace(&div);
+    renamed
+}
+
+// Returns the element replacing the div, if any.
+fn transform_div(div: &ElemRef) -> Option<NodeRef> {
     debug_assert_eq!(div.name, tag!("div"));
 
     let node = div.as_node();
@@ -182,9 +349,10 @@ fn transform_div(div: &ElemRef) {
         trace!("    => replacing <{}> with inner <p>", format_tag(node));
         let p = node.children().elements().next().unwrap();
         node.replace(&p);
+        Some(p.as_node().clone())
     } else if !has_block_elem(node) {
         trace!("    => altering <{}> to <p>", format_tag(node));
-        node.rename(tag!("p"));
+        Some(node.rename(tag!("p")))
     } else {
         // TODO: move to upper level.
         for child in node.children() {
@@ -201,6 +369,8 @@ fn transform_div(div: &ElemRef) {
                 p.append(child.clone());
             }
         }
+
+        None
     }
 }
 
@@ -228,66 +398,80 @@ fn has_block_elem(node: &NodeRef) -> bool {
     })
 }
 
-fn count_chars(text: &str) -> (u32, u32) {
+// Returns the number of graphemes, commas and graphemes of scripts without word separators.
+fn count_chars(text: &str) -> (u32, u32, u32) {
     let mut char_cnt = 0;
     let mut comma_cnt = 0;
+    let mut unspaced_cnt = 0;
 
-    // TODO: what about graphemes?
-    let mut iter = text.trim().chars().peekable();
+    let mut iter = text.trim().graphemes(true).peekable();
+
+    while let Some(grapheme) = iter.next() {
+        let ch = grapheme.chars().next().unwrap();
 
-    while let Some(ch) = iter.next() {
         if ch.is_whitespace() {
-            if !iter.peek().unwrap().is_whitespace() {
+            // Combining marks after a trailing whitespace make up the last grapheme.
+            if iter.peek().is_some_and(|next| !next.starts_with(char::is_whitespace)) {
                 char_cnt += 1;
             }
-        } else if ch == ',' {
-            if iter.peek().map_or(true, |&c| c != ',') {
+        } else if is_comma(ch) {
+            if iter.peek().map_or(true, |&next| next != grapheme) {
                 char_cnt += 1;
                 comma_cnt += 1;
             }
         } else {
             char_cnt += 1;
+
+            if stats::is_unspaced(ch) {
+                unspaced_cnt += 1;
+            }
         }
     }
 
-    (char_cnt, comma_cnt)
+    (char_cnt, comma_cnt, unspaced_cnt)
+}
+
+fn is_comma(ch: char) -> bool {
+    // Latin, full-width, ideographic and Arabic commas.
+    matches!(ch, ',' | '，' | '、' | '،')
 }
 
 fn is_tag_to_score(tag: &QualName) -> bool {
     matches!{
         *tag,
-        tag!("section") | tag!("p") | tag!("td") | tag!("pre") |
+        tag!("section") | tag!("p") | tag!("td") | tag!("pre") | tag!("figure") |
         tag!("h2") | tag!("h3") | tag!("h4") | tag!("h5") | tag!("h6")
     }
 }
 
-fn tag_score(tag: &QualName) -> f32 {
+fn tag_score(tag: &QualName, params: &ScoringParams) -> f32 {
     match *tag {
-        tag!("section") => 15.,
-        tag!("div") => 5.,
-        tag!("pre") | tag!("td") | tag!("blockquote") => 3.,
-        tag!("address") | tag!("form") => -3.,
-        tag!("dl") | tag!("dt") | tag!("dd") => -3.,
-        tag!("li") | tag!("ol") | tag!("ul") => -3.,
-        tag!("body") => -5.,
-        tag!("h1") | tag!("h2") | tag!("h3") | tag!("h4") | tag!("h5") | tag!("h6") => -5.,
-        tag!("th") => -5.,
+        tag!("section") => params.section_weight,
+        tag!("div") => params.div_weight,
+        tag!("pre") | tag!("td") | tag!("blockquote") => params.pre_weight,
+        tag!("address") | tag!("form") => params.form_weight,
+        tag!("dl") | tag!("dt") | tag!("dd") => params.definition_list_weight,
+        tag!("li") | tag!("ol") | tag!("ul") => params.list_weight,
+        tag!("body") => params.body_weight,
+        tag!("h1") | tag!("h2") | tag!("h3") | tag!("h4") | tag!("h5") | tag!("h6") =>
+            params.heading_weight,
+        tag!("th") => params.table_header_weight,
         _ => 0.
     }
 }
 
-fn class_score(elem: &ElemRef) -> f32 {
+fn class_score(elem: &ElemRef, heuristics: &Heuristics, weight: f32) -> f32 {
     let attributes = elem.attributes.borrow();
     let mut score = 0.;
 
     if let Some(classes) = attributes.get(attrib!("class")) {
-        if POSITIVE.is_match(classes) { score += 25.; }
-        if NEGATIVE.is_match(classes) { score -= 25.; }
+        if heuristics.positive.is_match(classes) { score += weight; }
+        if heuristics.negative.is_match(classes) { score -= weight; }
     }
 
     if let Some(id) = attributes.get(attrib!("id")) {
-        if POSITIVE.is_match(id) { score += 25.; }
-        if NEGATIVE.is_match(id) { score -= 25.; }
+        if heuristics.positive.is_match(id) { score += weight; }
+        if heuristics.negative.is_match(id) { score -= weight; }
     }
 
     score
@@ -295,12 +479,12 @@ fn class_score(elem: &ElemRef) -> f32 {
 
 fn is_stuffed(elem: &ElemRef, info: &NodeInfo) -> bool {
     match elem.name {
-        // TODO: remove <object>, <embed> etc.
         tag!("h1") | tag!("footer") | tag!("button") => false,
 
         tag!("div") | tag!("section") | tag!("header") |
         tag!("h2") | tag!("h3") | tag!("h4") | tag!("h5") | tag!("h6") => {
-            if info.text_len == 0 {
+            // Formulas are content even without text, e.g. an empty `<mspace>`.
+            if info.text_len == 0 && info.math_count == 0 {
                 let children_count = elem.as_node().children().count() as u32;
 
                 if children_count == 0 || children_count == info.br_count + info.hr_count {
@@ -312,12 +496,10 @@ fn is_stuffed(elem: &ElemRef, info: &NodeInfo) -> bool {
         },
 
         tag!("thead") | tag!("tbody") | tag!("th") | tag!("tr") | tag!("td") =>
-            // TODO: add <video> and <audio> counters to the sum.
-            info.text_len > 0 || info.img_count + info.embed_count + info.iframe_count > 0,
+            info.text_len > 0 || info.img_count + info.embed_count + info.media_count + info.math_count > 0,
 
         tag!("p") | tag!("pre") | tag!("blockquote") =>
-            // TODO: add <video> and <audio> counters to the sum.
-            info.img_count + info.embed_count + info.iframe_count > 0 ||
+            info.img_count + info.embed_count + info.media_count + info.math_count > 0 ||
             // TODO: calculate length without construction the string.
                 !elem.text_contents().trim().is_empty(),
 
@@ -325,8 +507,48 @@ fn is_stuffed(elem: &ElemRef, info: &NodeInfo) -> bool {
     }
 }
 
+fn is_svg_sprite(node: &NodeRef) -> bool {
+    node.children().elements().all(|child| matches!{
+        child.name.local,
+        local_name!("defs") | local_name!("symbol") | local_name!("use") | local_name!("title")
+    })
+}
+
+fn is_embed(elem: &ElementData) -> bool {
+    matches!(elem.name.local, local_name!("iframe") | local_name!("object") | local_name!("embed") | local_name!("video"))
+}
+
+// Media is allowed if its URL matches `Heuristic::Video`.
+// Video and audio hosted by the site itself are allowed too, unlike its frames, which are often ads.
+fn is_allowed_media(node: &NodeRef, elem: &ElementData, allowed_embeds: &PatternList, base_url: Option<&Url>) -> bool {
+    let is_self_hosted = |url: &str| {
+        let url = url.trim();
+
+        // Relative URLs, but not protocol-relative ones, which can point anywhere.
+        if !url.is_empty() && !PROTOCOL.is_match(url) && !url.starts_with("//") {
+            return true;
+        }
+
+        base_url.is_some_and(|base| base.join(url)
+            .is_ok_and(|url| url.host_str().is_some() && url.host_str() == base.host_str()))
+    };
+
+    let is_allowed_media_url = |url: &str| allowed_embeds.is_match(url) || is_self_hosted(url);
+
+    let attributes = elem.attributes.borrow();
+    let is_allowed = |name| attributes.get(name).is_some_and(|url| allowed_embeds.is_match(url));
+
+    match elem.name {
+        tag!("object") => is_allowed(attrib!("data")),
+        tag!("video") | tag!("audio") => attributes.get(attrib!("src")).is_some_and(is_allowed_media_url) ||
+            node.children().elements()
+                .filter(|child| child.is(tag!("source")))
+                .any(|source| source.attributes.borrow().get(attrib!("src")).is_some_and(is_allowed_media_url)),
+        _ => is_allowed(attrib!("src"))
+    }
+}
+
 fn clean_attributes(attributes: &mut Attributes) {
-    // TODO: what about removing all except for `alt`, `href`, `src` and `title`?
     attributes.remove(attrib!("style"));
 }
 
@@ -351,29 +573,112 @@ fn fix_relative_urls(attributes: &mut Attributes, base_url: &Url) {
     }
 }
 
+fn get_dir(elem: &ElemRef) -> Option<String> {
+    let attributes = elem.attributes.borrow();
+    let dir = attributes.get(attrib!("dir"))?.trim().to_ascii_lowercase();
+
+    // `auto` depends on the text, so it's left for the script detection.
+    matches!(&dir[..], "ltr" | "rtl").then_some(dir)
+}
+
+// Returns the direction declared by the node or its ancestors.
+fn find_dir(node: &NodeRef) -> Option<String> {
+    node.inclusive_ancestors().elements().find_map(|elem| get_dir(&elem))
+}
+
+// Removes the leading `<h1>` or `<h2>` if it repeats the title, like mozilla/readability does.
+fn remove_title_heading(content: &NodeRef, title: &str) {
+    let first_text = content.descendants().text_nodes()
+        .find(|text| !text.borrow().trim().is_empty());
+
+    let heading = first_text.and_then(|text| {
+        text.as_node().ancestors()
+            .take_while(|node| node != content)
+            .elements()
+            .find(|elem| elem.name == tag!("h1") || elem.name == tag!("h2"))
+    });
+
+    if let Some(heading) = heading {
+        if text_similarity(title, &heading.text_contents()) > 0.75 {
+            trace!("Removing <{}> duplicating the title", format_tag(&heading));
+            heading.as_node().detach();
+        }
+    }
+}
+
+// Returns the share of `b` (by length) made up of words that are also in `a`.
+fn text_similarity(a: &str, b: &str) -> f32 {
+    fn tokenize(text: &str) -> Vec<String> {
+        text.split(|c: char| !c.is_alphanumeric())
+            .filter(|token| !token.is_empty())
+            .map(str::to_lowercase)
+            .collect()
+    }
+
+    let (a, b) = (tokenize(a), tokenize(b));
+
+    if a.is_empty() || b.is_empty() {
+        return 0.;
+    }
+
+    let unique = b.iter().filter(|token| !a.contains(token)).cloned().collect::<Vec<_>>();
+    let distance = unique.join(" ").chars().count() as f32 / b.join(" ").chars().count() as f32;
+
+    1. - distance
+}
+
 fn is_acceptable_top_level(tag: &QualName) -> bool {
     matches!(*tag, tag!("div") | tag!("article") | tag!("section") | tag!("p"))
 }
 
+/// Information collected about a node during the extraction.
 #[derive(Default, PartialEq, Clone)]
-struct NodeInfo {
+pub struct NodeInfo {
     content_score: f32,
     text_len: u32,
     link_len: u32,
     commas: u32,
     is_candidate: bool,
     is_shabby: bool,
+    is_data_table: bool,
+    is_kept: bool,
 
     p_count: u32,
+    figure_count: u32,
     img_count: u32,
     li_count: u32,
     input_count: u32,
     embed_count: u32,
-    iframe_count: u32,
+    media_count: u32,
+    math_count: u32,
     br_count: u32,
     hr_count: u32,
 }
 
+impl NodeInfo {
+    /// The score accumulated from scored descendants.
+    pub fn content_score(&self) -> f32 { self.content_score }
+    /// The length of the text in graphemes, with characters of scripts without word separators
+    /// weighted by `ScoringParams::unspaced_char_weight`.
+    pub fn text_len(&self) -> u32 { self.text_len }
+    /// The length of the text inside of links.
+    pub fn link_len(&self) -> u32 { self.link_len }
+    pub fn link_density(&self) -> f32 { self.link_len as f32 / self.text_len as f32 }
+    pub fn commas(&self) -> u32 { self.commas }
+    pub fn is_candidate(&self) -> bool { self.is_candidate }
+    pub fn p_count(&self) -> u32 { self.p_count }
+    pub fn figure_count(&self) -> u32 { self.figure_count }
+    pub fn img_count(&self) -> u32 { self.img_count }
+    pub fn li_count(&self) -> u32 { self.li_count }
+    pub fn input_count(&self) -> u32 { self.input_count }
+    /// The number of embeds not matching `Heuristic::Video`.
+    pub fn embed_count(&self) -> u32 { self.embed_count }
+    /// The number of allowed media embeds.
+    pub fn media_count(&self) -> u32 { self.media_count }
+    /// The number of MathML formulas.
+    pub fn math_count(&self) -> u32 { self.math_count }
+}
+
 impl fmt::Debug for NodeInfo {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         let mut s = fmt.debug_struct("");
@@ -384,12 +689,16 @@ impl fmt::Debug for NodeInfo {
         if self.commas > 0 { s.field("commas", &self.commas); }
         if self.is_candidate { s.field("candidate", &self.is_candidate); }
         if self.is_shabby { s.field("shabby", &self.is_shabby); }
+        if self.is_data_table { s.field("data_table", &self.is_data_table); }
+        if self.is_kept { s.field("kept", &self.is_kept); }
         if self.p_count > 0 { s.field("p", &self.p_count); }
+        if self.figure_count > 0 { s.field("figure", &self.figure_count); }
         if self.img_count > 0 { s.field("img", &self.img_count); }
         if self.li_count > 0 { s.field("li", &self.li_count); }
         if self.input_count > 0 { s.field("input", &self.input_count); }
         if self.embed_count > 0 { s.field("embed", &self.embed_count); }
-        if self.iframe_count > 0 { s.field("iframe", &self.iframe_count); }
+        if self.media_count > 0 { s.field("media", &self.media_count); }
+        if self.math_count > 0 { s.field("math", &self.math_count); }
         if self.br_count > 0 { s.field("br", &self.br_count); }
         if self.hr_count > 0 { s.field("hr", &self.hr_count); }
 
@@ -406,6 +715,22 @@ pub struct Readability {
     weight_classes: bool,
     clean_conditionally: bool,
     clean_attributes: bool,
+    attribute_allowlist: Option<AttributeAllowlist>,
+    link_normalizer: Option<LinkNormalizer>,
+    remove_title_heading: bool,
+    sanitize: bool,
+    inject_heading_ids: bool,
+    excerpt_len: usize,
+    words_per_minute: u32,
+    cjk_chars_per_minute: u32,
+    heuristics: Heuristics,
+    scoring: ScoringParams,
+    strip_tags: HashSet<LocalName>,
+    keep_tags: HashSet<LocalName>,
+    remove_selectors: Vec<String>,
+    keep_selectors: Vec<String>,
+    site_rules: Option<Arc<SiteRules>>,
+    hooks: Vec<Box<dyn ExtractionHook>>,
     base_url: Option<Url>
 }
 
@@ -426,6 +751,22 @@ impl Readability {
             weight_classes: true,
             clean_conditionally: true,
             clean_attributes: true,
+            attribute_allowlist: None,
+            link_normalizer: None,
+            remove_title_heading: false,
+            sanitize: false,
+            inject_heading_ids: false,
+            excerpt_len: 200,
+            words_per_minute: 230,
+            cjk_chars_per_minute: 500,
+            heuristics: Heuristics::default(),
+            scoring: ScoringParams::default(),
+            strip_tags: STRIPPED_TAGS.iter().map(|&tag| LocalName::from(tag)).collect(),
+            keep_tags: HashSet::new(),
+            remove_selectors: Vec::new(),
+            keep_selectors: Vec::new(),
+            site_rules: None,
+            hooks: Vec::new(),
             base_url: None,
         }
     }
@@ -450,6 +791,158 @@ impl Readability {
         self
     }
 
+    /// Removes all attributes except for allowed ones instead of only `style`.
+    pub fn attribute_allowlist<A>(&mut self, allowlist: A) -> &mut Self
+        where A: Into<Option<AttributeAllowlist>>
+    {
+        self.attribute_allowlist = allowlist.into();
+        self
+    }
+
+    /// Removes tracking parameters and unwraps redirects in URLs of links.
+    pub fn link_normalizer<N>(&mut self, normalizer: N) -> &mut Self
+        where N: Into<Option<LinkNormalizer>>
+    {
+        self.link_normalizer = normalizer.into();
+        self
+    }
+
+    /// Removes the leading heading of the content if it repeats the article title.
+    pub fn remove_title_heading(&mut self, enabled: bool) -> &mut Self {
+        self.remove_title_heading = enabled;
+        self
+    }
+
+    /// Enforces a safe policy of tags, attributes and URL schemes on the content.
+    ///
+    /// Scripts, styles, forms, SVG and other active content are removed, as well as event handlers
+    /// and URLs with schemes other than `http`, `https`, `mailto` and `tel`.
+    /// Frames are kept only if they load a page matching `allowed_embeds`, even if kept by `keep_tag`.
+    /// Links get `rel="noopener noreferrer"`.
+    pub fn sanitize(&mut self, enabled: bool) -> &mut Self {
+        self.sanitize = enabled;
+        self
+    }
+
+    /// Sets ids of the table of contents to headings, so entries can link to them.
+    pub fn inject_heading_ids(&mut self, enabled: bool) -> &mut Self {
+        self.inject_heading_ids = enabled;
+        self
+    }
+
+    /// Sets the maximum length in chars of the excerpt generated if there is no description.
+    pub fn excerpt_length(&mut self, len: usize) -> &mut Self {
+        self.excerpt_len = len;
+        self
+    }
+
+    /// Sets the reading speed used to estimate the reading time.
+    pub fn words_per_minute(&mut self, wpm: u32) -> &mut Self {
+        self.words_per_minute = wpm;
+        self
+    }
+
+    /// Sets the reading speed of CJK text, which has no spaces between words.
+    pub fn cjk_chars_per_minute(&mut self, cpm: u32) -> &mut Self {
+        self.cjk_chars_per_minute = cpm;
+        self
+    }
+
+    /// Replaces patterns of `Heuristic::Video` with the regex, which is used as is.
+    ///
+    /// `<video>` and `<audio>` hosted by the site itself, i.e. with relative URLs or URLs on the host
+    /// of `base_url`, are kept regardless of the pattern. Frames, objects and embeds must match it.
+    pub fn allowed_embeds(&mut self, pattern: Regex) -> &mut Self {
+        self.heuristics.video.replace_with_regex(pattern);
+        self
+    }
+
+    pub fn scoring(&mut self, params: ScoringParams) -> &mut Self {
+        self.scoring = params;
+        self
+    }
+
+    /// Adds patterns to the given heuristic list.
+    ///
+    /// Patterns are regular expressions matched case-insensitively.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the patterns is an invalid regular expression.
+    pub fn extend_heuristic<I, S>(&mut self, heuristic: Heuristic, patterns: I) -> &mut Self
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        self.heuristics.get_mut(heuristic).extend(patterns);
+        self
+    }
+
+    /// Replaces the given heuristic list with new patterns.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any of the patterns is an invalid regular expression.
+    pub fn replace_heuristic<I, S>(&mut self, heuristic: Heuristic, patterns: I) -> &mut Self
+        where I: IntoIterator<Item = S>, S: Into<String>
+    {
+        self.heuristics.get_mut(heuristic).replace(patterns);
+        self
+    }
+
+    /// Always removes elements with the given tag name.
+    ///
+    /// Media embeds (`<iframe>`, `<object>`, `<embed>` and `<video>`) matching `allowed_embeds`
+    /// are kept anyway, and `<svg>` is removed only if it's an icon sprite.
+    pub fn strip_tag(&mut self, tag: &str) -> &mut Self {
+        self.strip_tags.insert(LocalName::from(tag.to_ascii_lowercase()));
+        self
+    }
+
+    /// Always keeps elements with the given tag name, overriding both `strip_tag` and
+    /// the cleaning heuristics.
+    pub fn keep_tag(&mut self, tag: &str) -> &mut Self {
+        self.keep_tags.insert(LocalName::from(tag.to_ascii_lowercase()));
+        self
+    }
+
+    /// Removes elements matching the CSS selector before extraction.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the selector is invalid.
+    pub fn remove_selector(&mut self, selector: &str) -> &mut Self {
+        Selectors::compile(selector).expect("invalid selector");
+        self.remove_selectors.push(selector.to_string());
+        self
+    }
+
+    /// Exempts elements matching the CSS selector, along with their descendants,
+    /// from the cleaning heuristics.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the selector is invalid.
+    pub fn keep_selector(&mut self, selector: &str) -> &mut Self {
+        Selectors::compile(selector).expect("invalid selector");
+        self.keep_selectors.push(selector.to_string());
+        self
+    }
+
+    /// Uses site-specific rules chosen by the host of `base_url`.
+    pub fn site_rules<R>(&mut self, rules: R) -> &mut Self
+        where R: Into<Arc<SiteRules>>
+    {
+        self.site_rules = Some(rules.into());
+        self
+    }
+
+    /// Registers a hook called at the stages of the extraction pipeline.
+    pub fn hook<H>(&mut self, hook: H) -> &mut Self
+        where H: ExtractionHook + 'static
+    {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
     pub fn base_url<U>(&mut self, url: U) -> &mut Self
         where U: Into<Option<Url>>
     {
@@ -458,17 +951,336 @@ impl Readability {
     }
 
     pub fn parse(&mut self, html: &str) -> (NodeRef, Metadata) {
+        let (content, mut metadata) = self.parse_document(html);
+        self.finish(&content, &mut metadata);
+        (content, metadata)
+    }
+
+    /// Extracts the content of an article split into several pages and joins it.
+    ///
+    /// Pages must be given in order along with their URLs, which are used instead of `base_url`.
+    /// Paragraphs repeating ones of previous pages are removed.
+    /// The metadata is taken from the first page, except for `next_page_url` taken from the last one.
+    pub fn parse_pages<I, S>(&mut self, pages: I) -> (NodeRef, Metadata)
+        where I: IntoIterator<Item = (Url, S)>, S: AsRef<str>
+    {
+        let base_url = self.base_url.take();
+        let mut first_url = None;
+
+        let article = NodeRef::new_element(tag!("div"), iter::empty());
+        let mut metadata: Option<Metadata> = None;
+        let mut seen = HashSet::new();
+
+        for (url, page) in pages {
+            first_url.get_or_insert_with(|| url.clone());
+            self.base_url = Some(url);
+
+            let (content, page_metadata) = self.parse_document(page.as_ref());
+            let mut texts = Vec::new();
+
+            for paragraph in content.select("p").unwrap().collect::<Vec<_>>() {
+                let text = paragraph.text_contents().split_whitespace().collect::<Vec<_>>().join(" ");
+
+                if text.is_empty() {
+                    continue;
+                }
+
+                if seen.contains(&text) {
+                    trace!("Removing the paragraph repeated on a previous page");
+                    paragraph.as_node().detach();
+                } else {
+                    texts.push(text);
+                }
+            }
+
+            seen.extend(texts);
+
+            // The body can be the best candidate, but it cannot be nested.
+            if content.as_element().is_some_and(|elem| elem.name == tag!("body")) {
+                let page = NodeRef::new_element(tag!("div"), iter::empty());
+
+                for child in content.children().collect::<Vec<_>>() {
+                    page.append(child);
+                }
+
+                article.append(page);
+            } else {
+                article.append(content);
+            }
+
+            match metadata {
+                Some(ref mut metadata) => metadata.next_page_url = page_metadata.next_page_url,
+                None => metadata = Some(page_metadata)
+            }
+        }
+
+        let mut metadata = metadata.unwrap_or_default();
+
+        // Links of the joined article are checked against the first page.
+        self.base_url = first_url;
+        self.finish(&article, &mut metadata);
+        self.base_url = base_url;
+
+        (article, metadata)
+    }
+
+    // Extracts the content of a single document without the final processing.
+    fn parse_document(&mut self, html: &str) -> (NodeRef, Metadata) {
+        // The instance can be reused, so forget about previous documents.
+        self.info = NodeCache::new();
+        self.candidates.clear();
+        self.byline = None;
+
+        let mut html = Cow::Borrowed(html);
+
+        for hook in &mut self.hooks {
+            if let Some(replacement) = hook.before_parse(&html) {
+                html = Cow::Owned(replacement);
+            }
+        }
+
+        let (content, mut metadata) = self.extract(&html);
+
+        if self.remove_title_heading {
+            if let Some(ref title) = metadata.article_title {
+                remove_title_heading(&content, title);
+            }
+        }
+
+        if metadata.has_poor_description() {
+            let min_len = self.scoring.min_paragraph_len as usize;
+
+            if let Some(excerpt) = metadata::generate_excerpt(&content, min_len, self.excerpt_len) {
+                metadata.description = Some(excerpt);
+                metadata.is_description_generated = true;
+            }
+        }
+
+        (content, metadata)
+    }
+
+    // Processes the final content once, even if it's joined from several pages.
+    fn finish(&mut self, content: &NodeRef, metadata: &mut Metadata) {
+        self.analyze(content, metadata);
+
+        for hook in &mut self.hooks {
+            hook.post_process(content, metadata);
+        }
+
+        // Sanitize last, so hooks cannot bring unsafe content back.
+        if self.sanitize {
+            sanitize::sanitize(content, &self.heuristics.video);
+        }
+
+        // Inventories must list only elements of the returned content.
+        metadata.links = inventory::collect_links(content, self.base_url.as_ref());
+        metadata.images = inventory::collect_images(content, self.base_url.as_ref());
+        metadata.footnotes = footnotes::collect(content);
+        metadata.toc = toc::build(content, self.inject_heading_ids);
+    }
+
+    // Collects information about the final content.
+    fn analyze(&self, content: &NodeRef, metadata: &mut Metadata) {
+        let text = stats::text_of(content);
+        let (word_cnt, cjk_cnt) = stats::count_words(&text);
+        let img_cnt = content.select("img").unwrap().count() as u32;
+
+        metadata.word_count = word_cnt;
+        metadata.char_count = count_chars(&text).0;
+        metadata.reading_time = stats::reading_time(
+            word_cnt, cjk_cnt, img_cnt, self.words_per_minute, self.cjk_chars_per_minute);
+
+        let is_dir_declared = metadata.dir.is_some();
+
+        if !is_dir_declared {
+            metadata.dir = stats::detect_direction(&text).map(String::from);
+        }
+
+        // Set the direction explicitly, because the content is detached from the document.
+        // Left-to-right is the default, so it's set only if declared.
+        if let (Some(dir), Some(root)) = (&metadata.dir, content.as_element()) {
+            let mut attributes = root.attributes.borrow_mut();
+
+            if !attributes.contains(attrib!("dir")) && (is_dir_declared || dir == "rtl") {
+                attributes.insert(attrib!("dir"), dir.clone());
+            }
+        }
+
+        metadata.detected_lang = language::detect(&text);
+        metadata.lang = language::best_guess(metadata.declared_lang.as_deref(), metadata.detected_lang.as_ref());
+
+    }
+
+    fn extract(&mut self, html: &str) -> (NodeRef, Metadata) {
         let top_level = kuchiki::parse_html().one(html);
+        code::normalize(&top_level);
+        math::normalize(&top_level);
+
+        let site_rules = self.site_rules.clone();
+        let host = self.base_url.as_ref().and_then(|url| url.host_str());
+        let site_rule = site_rules.as_ref().zip(host).and_then(|(rules, host)| rules.find(host));
+
+        if let Some(rule) = site_rule {
+            trace!("Applying the site rule for {}", host.unwrap());
+            rule.strip(&top_level);
+        }
+
+        self.remove_selected(&top_level);
+
+        let mut metadata = metadata::extract(&top_level);
+
+        // The content is detached from `<html>`, so remember its direction.
+        let html_dir = top_level.select_first("html").ok().and_then(|html| get_dir(&html));
+        let body_dir = top_level.select_first("body").ok().and_then(|body| get_dir(&body));
 
-        let metadata = metadata::extract(&top_level);
+        metadata.next_page_url = self.base_url.as_ref()
+            .and_then(|url| pagination::find_next_page(&top_level, url, &self.heuristics))
+            .map(String::from);
+
+        if let Some(rule) = site_rule {
+            metadata.article_title = rule.extract_title(&top_level).or(metadata.article_title);
+            metadata.byline = rule.extract_author(&top_level).or(metadata.byline);
+            metadata.published_time = rule.extract_date(&top_level).or(metadata.published_time);
+
+            if !rule.prefer_generic {
+                if let Some(body) = rule.extract_body(&top_level) {
+                    footnotes::append_missing(&body, &footnotes::find_sections(&top_level));
+                    self.strip_tree(&body);
+                    self.clean_tree(&body);
+                    metadata.dir = find_dir(&body).or(body_dir).or(html_dir);
+                    return (body, metadata);
+                }
+            }
+        }
 
         let top_level = top_level.select("html > body").unwrap().next()
             .map_or(top_level, |b| b.as_node().clone());
 
         top_level.detach();
 
+        // Footnotes are often removed as lists of links, but references to them stay.
+        let footnote_sections = footnotes::find_sections(&top_level);
+
+        for section in &footnote_sections {
+            for node in footnotes::skeleton(section) {
+                self.info.get_or_create(&node).is_kept = true;
+            }
+        }
+
+        for selector in &self.keep_selectors {
+            for elem in top_level.select(selector).unwrap() {
+                for node in elem.as_node().inclusive_descendants() {
+                    self.info.get_or_create(&node).is_kept = true;
+                }
+            }
+        }
+
         // TODO: retry with fewer restrictions.
-        (self.readify(top_level), metadata)
+        let content = self.readify(top_level.clone());
+
+        // The generic extraction has failed, so fall back to the site rule.
+        if let Some(rule) = site_rule.filter(|rule| rule.prefer_generic && content == top_level) {
+            let top_level = kuchiki::parse_html().one(html);
+            code::normalize(&top_level);
+            math::normalize(&top_level);
+            rule.strip(&top_level);
+            self.remove_selected(&top_level);
+
+            if let Some(body) = rule.extract_body(&top_level) {
+                footnotes::append_missing(&body, &footnotes::find_sections(&top_level));
+                self.strip_tree(&body);
+                self.clean_tree(&body);
+                metadata.dir = find_dir(&body).or(body_dir).or(html_dir);
+                return (body, metadata);
+            }
+        }
+
+        if self.attribute_allowlist.is_some() {
+            for elem in content.inclusive_descendants().elements() {
+                self.clean_element_attributes(&elem.name.local, &mut elem.attributes.borrow_mut());
+            }
+        }
+
+        for section in footnotes::append_missing(&content, &footnote_sections) {
+            self.clean_tree(&section);
+        }
+
+        metadata.dir = find_dir(&content).or(html_dir);
+
+        (content, metadata)
+    }
+
+    fn remove_selected(&self, top_level: &NodeRef) {
+        for selector in &self.remove_selectors {
+            let matches = top_level.select(selector).unwrap().collect::<Vec<_>>();
+
+            for elem in matches {
+                trace!("Removing <{}> matching \"{}\"", format_tag(&elem), selector);
+                elem.remove();
+            }
+        }
+    }
+
+    // Applies the same removals as the capturing stage to an extracted tree.
+    fn strip_tree(&mut self, root: &NodeRef) {
+        for node in root.descendants().collect::<Vec<_>>() {
+            // Skip nodes removed along with their ancestors.
+            if !node.ancestors().any(|ancestor| ancestor == *root) {
+                continue;
+            }
+
+            if self.is_useless(&node) {
+                trace!("Removing useless node of the extracted body");
+                node.detach();
+            } else if let Some(elem) = node.into_element_ref() {
+                self.apply_capture_hooks(&elem);
+            }
+        }
+    }
+
+    // Applies the same per-element cleaning as the bubbling stage to an extracted tree.
+    fn clean_tree(&self, node: &NodeRef) {
+        for elem in node.inclusive_descendants().elements() {
+            let mut attributes = elem.attributes.borrow_mut();
+
+            self.clean_element_attributes(&elem.name.local, &mut attributes);
+            self.fix_element_urls(&elem.name.local, &mut attributes);
+        }
+    }
+
+    fn clean_element_attributes(&self, tag: &LocalName, attributes: &mut Attributes) {
+        match self.attribute_allowlist {
+            Some(ref allowlist) => {
+                // References and footnotes link to each other by ids.
+                let id = attributes.get(attrib!("id"))
+                    .filter(|id| footnotes::is_footnote_id(id))
+                    .map(String::from);
+
+                allowlist.clean(tag, attributes);
+
+                if let Some(id) = id {
+                    attributes.insert(attrib!("id"), id);
+                }
+            },
+            None if self.clean_attributes => clean_attributes(attributes),
+            None => {}
+        }
+    }
+
+    fn fix_element_urls(&self, tag: &LocalName, attributes: &mut Attributes) {
+        if *tag != local_name!("a") && *tag != local_name!("img") {
+            return;
+        }
+
+        if let Some(ref base_url) = self.base_url {
+            fix_relative_urls(attributes, base_url);
+        }
+
+        if let Some(ref normalizer) = self.link_normalizer {
+            if *tag == local_name!("a") {
+                normalizer.normalize_link(attributes);
+            }
+        }
     }
 
     fn readify(&mut self, top_level: NodeRef) -> NodeRef {
@@ -528,23 +1340,33 @@ impl Readability {
 
         for child in node.children() {
             let remove = match *child.data() {
-                NodeData::Comment(_) | NodeData::DocumentFragment => true,
-                NodeData::Text(ref data) => data.borrow().trim().is_empty(),
-                NodeData::Element(ref elem) => {
-                    matches!(elem.name, tag!("script") | tag!("style") | tag!("noscript"))
-                },
-                _ => false
+                // Whitespaces of code are meaningful.
+                NodeData::Text(ref data) => data.borrow().trim().is_empty() && !code::is_in_code(node),
+                _ => self.is_useless(&child)
             };
 
             if remove {
-                if child.as_element().is_some() {
+                if let Some(elem) = child.as_element() {
                     trace!("    => removing <{}> as useless element", format_tag(&child));
+
+                    // Unallowed embeds make containers conditionally unacceptable, so do the same
+                    // for the parent to prevent `correct_candidate` from climbing over it.
+                    if is_embed(elem) && self.is_stripped(&child, elem) {
+                        self.info.get_or_create(node).is_shabby = true;
+                    }
                 }
 
                 child.remove();
             }
 
-            if let Some(child) = child.into_element_ref() {
+            if let Some(mut child) = child.into_element_ref() {
+                if let Some(renamed) = self.apply_capture_hooks(&child) {
+                    match renamed {
+                        Some(renamed) => child = renamed,
+                        None => continue
+                    }
+                }
+
                 // TODO: mozilla/readability takes into account only first occurrence.
                 //if self.byline.is_none() {
                     if let Some(byline) = extract_byline(&child) {
@@ -556,29 +1378,90 @@ impl Readability {
                     }
                 //}
 
-                if self.strip_unlikelys && is_unlikely_candidate(&child) {
+                // Captions must stay attached to their images.
+                if self.strip_unlikelys && !self.is_kept(&child) &&
+                    is_unlikely_candidate(&child, &self.heuristics) &&
+                    !has_figure_ancestor(child.as_node()) && !code::is_in_code(child.as_node())
+                {
                     trace!("    => removing <{}> as unlikely candidate", format_tag(&child));
                     child.remove();
-                } else if child.is(tag!("div")) {
-                    transform_div(&child);
+                } else if child.is(tag!("div")) && !code::is_in_code(child.as_node()) {
+                    if let Some(replacement) = transform_div(&child) {
+                        self.carry_over(child.as_node(), &replacement);
+                    }
+                } else if child.is(tag!("table")) {
+                    if is_data_table(&child) {
+                        trace!("    => marking <{}> as data table", format_tag(&child));
+                        self.info.get_or_create(child.as_node()).is_data_table = true;
+                    } else {
+                        trace!("    => unwrapping <{}> as layout table", format_tag(&child));
+
+                        for (cell, replacement) in unwrap_layout_table(&child) {
+                            self.carry_over(&cell, &replacement);
+                        }
+                    }
                 } else if child.is(tag!("font")) {
                     trace!("    => altering <{}> to <div>", format_tag(&child));
-                    child.rename(tag!("span"));
+                    let replacement = child.rename(tag!("span"));
+                    self.carry_over(child.as_node(), &replacement);
+                }
+            }
+        }
+    }
+
+    // Elements are replaced on renaming, so the replacement must be kept as well.
+    fn carry_over(&mut self, node: &NodeRef, replacement: &NodeRef) {
+        if self.info.get(node).is_some_and(|info| info.is_kept) {
+            self.info.get_or_create(replacement).is_kept = true;
+        }
+    }
+
+    // Returns `Some(None)` if the element has been removed and `Some(renamed)` if renamed.
+    fn apply_capture_hooks(&mut self, elem: &ElemRef) -> Option<Option<ElemRef>> {
+        let mut result = None;
+
+        for hook in &mut self.hooks {
+            let current = result.as_ref().and_then(Option::as_ref).unwrap_or(elem);
+
+            match hook.on_capture(current) {
+                CaptureAction::Continue => {},
+                CaptureAction::Remove => {
+                    trace!("    => removing <{}> by hook", format_tag(current));
+                    current.remove();
+                    return Some(None);
+                },
+                CaptureAction::Rename(name) => {
+                    trace!("    => altering <{}> to <{}> by hook", format_tag(current), name);
+                    let name = QualName::new(None, ns!(html), name);
+                    let replacement = current.rename(name);
+
+                    // Hooks are borrowed, so `carry_over` can't be used here.
+                    if self.info.get(current.as_node()).is_some_and(|info| info.is_kept) {
+                        self.info.get_or_create(&replacement).is_kept = true;
+                    }
+
+                    result = Some(replacement.into_element_ref());
                 }
             }
         }
+
+        result
     }
 
     // Bubbling stage: collect info based on children and score elements.
     fn on_bubbling(&mut self, node: &NodeRef) {
         match *node.data() {
             NodeData::Text(ref data) => {
-                let (char_cnt, comma_cnt) = count_chars(&data.borrow()[..]);
+                let (char_cnt, comma_cnt, unspaced_cnt) = count_chars(&data.borrow()[..]);
+
+                // A character of scripts without word separators carries more information.
+                let weight = self.scoring.unspaced_char_weight.max(1);
+                let text_len = char_cnt + unspaced_cnt * (weight - 1);
 
                 let parent = node.parent().unwrap();
 
                 let parent_info = self.info.get_or_create(&parent);
-                parent_info.text_len += char_cnt;
+                parent_info.text_len += text_len;
                 parent_info.commas += comma_cnt;
             },
             NodeData::Element(ElementData { ref name, ref attributes, .. }) => {
@@ -593,15 +1476,47 @@ impl Readability {
 
                 let elem = node.clone().into_element_ref().unwrap();
 
+                let mut is_kept = false;
+
+                for hook in &mut self.hooks {
+                    match hook.on_bubble(&elem, self.info.get_or_create(node)) {
+                        BubbleAction::Continue => {},
+                        BubbleAction::Remove => {
+                            // The node and its descendants can be already scored.
+                            for node in node.inclusive_descendants() {
+                                if let Some(info) = self.info.get(&node) {
+                                    info.is_candidate = false;
+                                }
+                            }
+
+                            node.remove();
+                            trace!("    => removed by hook");
+
+                            return;
+                        },
+                        BubbleAction::Keep => is_kept = true
+                    }
+                }
+
+                // Data tables are preserved intact, including empty cells.
+                let is_protected = is_kept || self.is_kept(&elem) || self.is_in_data_table(node);
+
                 // TODO: don't create info if it's not necessary.
-                if !is_stuffed(&elem, self.info.get_or_create(node)) {
+                if !is_protected && !is_stuffed(&elem, self.info.get_or_create(node)) {
+                    // The junk has been stripped from the node, but it still was in the parent.
+                    if self.info.get_or_create(node).is_shabby {
+                        if let Some(info) = node.parent().map(|parent| self.info.get_or_create(&parent)) {
+                            info.is_shabby = true;
+                        }
+                    }
+
                     node.remove();
                     trace!("    => removed (it's not stuffed)");
 
                     return;
                 }
 
-                if self.clean_conditionally && !self.is_conditionally_acceptable(&elem) {
+                if !is_protected && self.clean_conditionally && !self.is_conditionally_acceptable(&elem) {
                     if let Some(info) = self.info.get(node) {
                         info.is_candidate = false;
                     }
@@ -627,15 +1542,12 @@ impl Readability {
 
                 let mut attributes = attributes.borrow_mut();
 
-                if self.clean_attributes {
-                    clean_attributes(&mut attributes);
+                // Classes and ids are used for scoring, so the allowlist is applied to the content later.
+                if self.attribute_allowlist.is_none() {
+                    self.clean_element_attributes(&name.local, &mut attributes);
                 }
 
-                if let Some(ref base_url) = self.base_url {
-                    if *name == tag!("a") || *name == tag!("img") {
-                        fix_relative_urls(&mut attributes, base_url);
-                    }
-                }
+                self.fix_element_urls(&name.local, &mut attributes);
             },
             _ => {}
         };
@@ -651,8 +1563,14 @@ impl Readability {
         // TODO: avoid extra cloning.
         let info = self.info.get_or_create(node).clone();
 
+        let is_figure = node.clone().into_element_ref().is_some_and(|elem| is_figure(&elem, &info));
+
         let parent_info = self.info.get_or_create(&parent);
 
+        if is_figure {
+            parent_info.figure_count += 1;
+        }
+
         if let Some(elem) = node.as_element() {
             match elem.name {
                 tag!("p") => parent_info.p_count += 1,
@@ -661,15 +1579,14 @@ impl Readability {
                 tag!("input") => parent_info.input_count += 1,
                 tag!("br") => parent_info.br_count += 1,
                 tag!("hr") => parent_info.hr_count += 1,
-                tag!("iframe") => parent_info.iframe_count += 1,
-                tag!("embed") => {
-                    let attribs = elem.attributes.borrow();
-                    let src = attribs.get(attrib!("src")).unwrap_or("");
-
-                    if !VIDEO.is_match(src) {
+                tag!("iframe") | tag!("object") | tag!("embed") | tag!("video") | tag!("audio") => {
+                    if is_allowed_media(node, elem, &self.heuristics.video, self.base_url.as_ref()) {
+                        parent_info.media_count += 1;
+                    } else {
                         parent_info.embed_count += 1;
                     }
                 },
+                _ if math::is_math(elem) => parent_info.math_count += 1,
                 _ => {}
             };
         }
@@ -678,15 +1595,53 @@ impl Readability {
         parent_info.text_len += info.text_len;
         parent_info.commas += info.commas;
         parent_info.p_count += info.p_count;
+        parent_info.figure_count += info.figure_count;
         parent_info.img_count += info.img_count;
         parent_info.li_count += info.li_count;
         parent_info.input_count += info.input_count;
         parent_info.embed_count += info.embed_count;
-        parent_info.iframe_count += info.iframe_count;
+        parent_info.media_count += info.media_count;
+        parent_info.math_count += info.math_count;
         parent_info.br_count += info.br_count;
         parent_info.hr_count += info.hr_count;
     }
 
+    fn is_kept(&mut self, elem: &ElemRef) -> bool {
+        self.keep_tags.contains(&elem.name.local) ||
+            self.info.get(elem.as_node()).is_some_and(|info| info.is_kept)
+    }
+
+    // Checks if the node is removed at the capturing stage regardless of its content.
+    fn is_useless(&self, node: &NodeRef) -> bool {
+        match *node.data() {
+            NodeData::Comment(_) | NodeData::DocumentFragment => true,
+            NodeData::Element(ref elem) => {
+                matches!(elem.name, tag!("script") | tag!("style") | tag!("noscript")) ||
+                    self.is_stripped(node, elem)
+            },
+            _ => false
+        }
+    }
+
+    fn is_stripped(&self, node: &NodeRef, elem: &ElementData) -> bool {
+        let tag = &elem.name.local;
+
+        if self.keep_tags.contains(tag) || !self.strip_tags.contains(tag) {
+            return false;
+        }
+
+        match *tag {
+            _ if is_embed(elem) => !is_allowed_media(node, elem, &self.heuristics.video, self.base_url.as_ref()),
+            local_name!("svg") => is_svg_sprite(node),
+            _ => true
+        }
+    }
+
+    fn is_in_data_table(&mut self, node: &NodeRef) -> bool {
+        node.ancestors().filter(|ancestor| ancestor.is(tag!("table")))
+            .any(|table| self.info.get(&table).is_some_and(|info| info.is_data_table))
+    }
+
     fn is_conditionally_acceptable(&mut self, elem: &ElemRef) -> bool {
         let is_list = match elem.name {
             tag!("form") | tag!("fieldset") | tag!("table") | tag!("div") => false,
@@ -694,31 +1649,56 @@ impl Readability {
             _ => return true
         };
 
+        let info = self.info.get_or_create(elem.as_node());
+
+        if info.is_data_table {
+            return true;
+        }
+
+        // Figures are content by definition, so they are never cleaned conditionally.
+        if is_figure(elem, info) || has_figure_ancestor(elem.as_node()) {
+            return true;
+        }
+
+        // Allowed media embeds are kept along with their containers.
+        if info.media_count > 0 {
+            return true;
+        }
+
         // TODO: cache the score to prevent extra calculations.
-        let class_score = if self.weight_classes { class_score(elem) } else { 0. };
+        let class_score = if self.weight_classes {
+            class_score(elem, &self.heuristics, self.scoring.class_weight)
+        } else {
+            0.
+        };
 
         if class_score < 0. {
             return false;
         }
 
-        let info = self.info.get_or_create(elem.as_node());
+        let params = &self.scoring;
 
-        if info.commas >= 10 {
+        if info.commas >= params.min_commas {
             return true;
         }
 
         let link_density = info.link_len as f32 / info.text_len as f32;
-        let p_img_ratio = info.p_count as f32 / info.img_count as f32;
 
-        // TODO: take into account ancestor tags (check "figure").
+        // Code blocks and formulas can be short, but they are content anyway.
+        let has_code_or_math = info.math_count > 0 || elem.as_node().descendants().any(|node| node.is(tag!("pre")));
+
+        // Every figure holds its own image, so count it as a paragraph.
+        let p_img_ratio = (info.p_count + info.figure_count) as f32 / info.img_count as f32;
+
         !(
-            (info.img_count > 1 && p_img_ratio < 0.5) ||
-            (!is_list && info.li_count > info.p_count + 100) ||
-            (info.input_count * 3 > info.p_count) ||
-            (!is_list && info.text_len < 25 && (info.img_count == 0 || info.img_count > 2)) ||
-            (!is_list && class_score < 25. && link_density > 0.2) ||
-            (class_score >= 25. && link_density > 0.5) ||
-            ((info.embed_count == 1 && info.text_len < 75) || info.embed_count > 1)
+            (info.img_count > 1 && p_img_ratio < params.min_p_img_ratio) ||
+            (!is_list && info.li_count > info.p_count + params.max_li_excess) ||
+            (info.input_count * params.input_ratio > info.p_count) ||
+            (!is_list && !has_code_or_math && info.text_len < params.min_text_len &&
+                (info.img_count == 0 || info.img_count > params.max_short_node_imgs)) ||
+            (!is_list && class_score < params.class_weight && link_density > params.max_link_density) ||
+            (class_score >= params.class_weight && link_density > params.max_weighted_link_density) ||
+            ((info.embed_count == 1 && info.text_len < params.min_embed_text_len) || info.embed_count > 1)
          )
     }
 
@@ -738,7 +1718,9 @@ impl Readability {
 
         let info = self.info.get_or_create(node);
 
-        if info.text_len < 25 {
+        let params = &self.scoring;
+
+        if info.text_len < params.min_paragraph_len {
             return None;
         }
 
@@ -750,13 +1732,15 @@ impl Readability {
 
         // For every 100 characters in this paragraph, add another point. Up to 3 points.
         let total_len = info.text_len + info.link_len;
-        content_score += cmp::min(total_len / 100, 3);
+        content_score += cmp::min(total_len / params.chars_per_point.max(1), params.max_length_points);
 
         Some(content_score as f32)
     }
 
     fn propagate_score(&mut self, node: &NodeRef, content_score: f32) {
-        for (level, ancestor) in node.ancestors().elements().enumerate().take(3) {
+        let levels = self.scoring.ancestor_levels;
+
+        for (level, ancestor) in node.ancestors().elements().enumerate().take(levels) {
             let div = match level {
                 0 => 1.,
                 1 => 2.,
@@ -800,17 +1784,21 @@ impl Readability {
             let mut score = info.content_score;
 
             // Add points for tag name.
-            score += tag_score(&candidate.name);
+            score += tag_score(&candidate.name, &self.scoring);
 
             // Add points for an class/id weight.
             if self.weight_classes {
-                score += class_score(&candidate);
+                score += class_score(&candidate, &self.heuristics, self.scoring.class_weight);
             }
 
             // Scale the final score based on link density. Good content should have a relatively
             // small link density (5% or less) and be mostly unaffected by this operation.
             score *= 1. - info.link_len as f32 / info.text_len as f32;
 
+            for hook in &mut self.hooks {
+                score = hook.adjust_score(&candidate, info, score);
+            }
+
             trace!("    => score: {}", score);
 
             debug_assert!(score.is_finite());
@@ -825,7 +1813,7 @@ impl Readability {
 
         scored_candidates.sort_by(|&(a, _), &(b, _)| b.partial_cmp(&a).unwrap());
 
-        let score_threshold = scored_candidates[0].0 * 0.75;
+        let score_threshold = scored_candidates[0].0 * self.scoring.top_candidate_ratio;
 
         let top_candidate_it = scored_candidates.into_iter()
             .take_while(|&(score, _)| score >= score_threshold)
@@ -837,14 +1825,13 @@ impl Readability {
     }
 
     fn find_common_candidate(&self) -> NodeRef {
-        // TODO: mozilla/readability uses 3 here, but we still have problems.
-        const MIN_CANDIDATES: usize = 4;
+        let min_candidates = self.scoring.min_candidates;
 
         trace!("Searching for common parent...");
 
         let best = self.candidates[0].as_node();
 
-        if self.candidates.len() < MIN_CANDIDATES ||
+        if self.candidates.len() < min_candidates ||
            best.is(tag!("body")) || best.parent().map_or(true, |p| p.is(tag!("body"))) {
             return best.clone();
         }
@@ -857,7 +1844,7 @@ impl Readability {
                     n += 1;
                 }
 
-                if n == MIN_CANDIDATES {
+                if n == min_candidates {
                     trace!("Found common parent of top candidates: <{}>", format_tag(&common));
                     return common;
                 }
@@ -930,3 +1917,15 @@ fn format_tag<N: NodeRefExt>(node: &N) -> String {
 fn format_info(info: Option<&mut NodeInfo>) -> String {
     info.map_or_else(String::new, |i| format!("{:?}", i))
 }
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+
+    #[test]
+    fn test_count_chars() {
+        assert_eq!(count_chars("a  b, c"), (6, 1, 0));
+        assert_eq!(count_chars("end a \u{301}"), (5, 0, 0));
+    }
+}
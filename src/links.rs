@@ -0,0 +1,250 @@
+use html5ever::local_name;
+use kuchiki::Attributes;
+use url::Url;
+
+
+const BLOCKED_PARAMS: [&str; 13] = [
+    "utm_*", "fbclid", "gclid", "dclid", "gclsrc", "msclkid", "yclid", "twclid", "igshid",
+    "mc_cid", "mc_eid", "_hsenc", "_hsmi",
+];
+
+const REDIRECTS: [(&str, &str, &str); 7] = [
+    ("l.facebook.com", "/l.php", "u"),
+    ("lm.facebook.com", "/l.php", "u"),
+    ("l.instagram.com", "/", "u"),
+    ("google.*", "/url", "q"),
+    ("google.*", "/url", "url"),
+    ("youtube.com", "/redirect", "q"),
+    ("out.reddit.com", "/", "url"),
+];
+
+// Shorteners whose targets are known only from attributes of the link, e.g. on Twitter pages.
+const SHORTENERS: [&str; 1] = ["t.co"];
+const EXPANDED_URL_ATTRS: [&str; 3] = ["data-expanded-url", "data-full-url", "title"];
+
+// Nested redirects are rare, but possible.
+const MAX_UNWRAPS: usize = 3;
+
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Redirect {
+    host: String,
+    path: String,
+    param: String,
+}
+
+/// Rules to clean up URLs of links: tracking parameters are removed and redirects are unwrapped.
+///
+/// Everything is done with the URL string only, without requests.
+/// The default rules remove `utm_*`, `fbclid`, `gclid` and similar parameters and unwrap redirects
+/// of Facebook, Google, YouTube and `t.co` (if the link has `data-expanded-url`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LinkNormalizer {
+    blocked_params: Vec<String>,
+    redirects: Vec<Redirect>,
+}
+
+impl LinkNormalizer {
+    /// Creates an empty normalizer, which leaves URLs as is.
+    pub fn new() -> LinkNormalizer {
+        LinkNormalizer {
+            blocked_params: Vec::new(),
+            redirects: Vec::new(),
+        }
+    }
+
+    /// Removes the query parameter, a trailing `*` matches any suffix, e.g. `utm_*`.
+    pub fn block_param(&mut self, param: &str) -> &mut Self {
+        let param = param.to_ascii_lowercase();
+
+        if !self.blocked_params.contains(&param) {
+            self.blocked_params.push(param);
+        }
+
+        self
+    }
+
+    /// Keeps the query parameter blocked before.
+    pub fn allow_param(&mut self, param: &str) -> &mut Self {
+        self.blocked_params.retain(|blocked| !blocked.eq_ignore_ascii_case(param));
+        self
+    }
+
+    /// Replaces URLs of the redirector with the value of the query parameter.
+    ///
+    /// `www.` is ignored, a trailing `.*` in the host matches any TLD, e.g. `google.*`.
+    pub fn unwrap_redirect(&mut self, host: &str, path: &str, param: &str) -> &mut Self {
+        self.redirects.push(Redirect {
+            host: host.trim_start_matches("www.").to_ascii_lowercase(),
+            path: path.to_string(),
+            param: param.to_string(),
+        });
+
+        self
+    }
+
+    /// Unwraps redirects and removes blocked parameters.
+    pub fn normalize(&self, url: &Url) -> Url {
+        let mut url = url.clone();
+
+        for _ in 0..MAX_UNWRAPS {
+            match self.unwrap(&url) {
+                Some(target) => url = target,
+                None => break
+            }
+        }
+
+        self.strip_params(&mut url);
+        url
+    }
+
+    // Normalizes `href` of the link, which must be already resolved.
+    pub(crate) fn normalize_link(&self, attributes: &mut Attributes) {
+        let url = match attributes.get(local_name!("href")).and_then(|href| Url::parse(href).ok()) {
+            Some(url) => url,
+            None => return
+        };
+
+        // Shorteners are unwrapped like redirects, so an empty normalizer leaves them as is.
+        let expanded = match expanded_url(&url, attributes) {
+            Some(expanded) if !self.redirects.is_empty() => expanded,
+            _ => url.clone()
+        };
+
+        let normalized = self.normalize(&expanded);
+
+        // Don't touch unchanged URLs, parsing can add a trailing slash.
+        if normalized != url {
+            attributes.insert(local_name!("href"), normalized.into());
+        }
+    }
+
+    fn unwrap(&self, url: &Url) -> Option<Url> {
+        let host = url.host_str()?.trim_start_matches("www.");
+
+        let redirect = self.redirects.iter()
+            .filter(|redirect| host_matches(&redirect.host, host) && redirect.path == url.path())
+            .find(|redirect| url.query_pairs().any(|(key, _)| key == *redirect.param))?;
+
+        let (_, target) = url.query_pairs().find(|(key, _)| *key == redirect.param)?;
+        parse_http(&target)
+    }
+
+    fn strip_params(&self, url: &mut Url) {
+        if url.query().is_none() {
+            return;
+        }
+
+        let pairs = url.query_pairs()
+            .map(|(key, value)| (key.into_owned(), value.into_owned()))
+            .collect::<Vec<_>>();
+
+        let kept = pairs.iter().filter(|(key, _)| !self.is_blocked(key)).collect::<Vec<_>>();
+
+        // Rebuild the query only if it's changed to keep the original encoding.
+        if kept.len() == pairs.len() {
+            return;
+        }
+
+        if kept.is_empty() {
+            url.set_query(None);
+        } else {
+            url.query_pairs_mut().clear().extend_pairs(kept);
+        }
+    }
+
+    fn is_blocked(&self, param: &str) -> bool {
+        let param = param.to_ascii_lowercase();
+
+        self.blocked_params.iter().any(|blocked| match blocked.strip_suffix('*') {
+            Some(prefix) => param.starts_with(prefix),
+            None => param == *blocked
+        })
+    }
+}
+
+impl Default for LinkNormalizer {
+    fn default() -> Self {
+        let mut normalizer = LinkNormalizer::new();
+
+        for param in &BLOCKED_PARAMS {
+            normalizer.block_param(param);
+        }
+
+        for (host, path, param) in &REDIRECTS {
+            normalizer.unwrap_redirect(host, path, param);
+        }
+
+        normalizer
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    match pattern.strip_suffix(".*") {
+        Some(name) => host.strip_prefix(name).is_some_and(|tld| tld.starts_with('.')),
+        None => pattern.eq_ignore_ascii_case(host)
+    }
+}
+
+// Shorteners hide the target, but pages often keep it in attributes of the link.
+fn expanded_url(url: &Url, attributes: &Attributes) -> Option<Url> {
+    if !SHORTENERS.contains(&url.host_str()?) {
+        return None;
+    }
+
+    EXPANDED_URL_ATTRS.iter()
+        .filter_map(|&attr| attributes.get(attr))
+        .find_map(|value| parse_http(value.trim()))
+}
+
+// Only web pages are accepted as targets, e.g. not `javascript:`.
+fn parse_http(url: &str) -> Option<Url> {
+    Url::parse(url).ok().filter(|url| matches!(url.scheme(), "http" | "https"))
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+
+    fn normalize(url: &str) -> String {
+        LinkNormalizer::default().normalize(&Url::parse(url).unwrap()).into()
+    }
+
+    #[test]
+    fn test_strip_params() {
+        assert_eq!(normalize("https://example.com/a?utm_source=x&utm_medium=y"), "https://example.com/a");
+        assert_eq!(normalize("https://example.com/a?id=1&fbclid=abc#top"), "https://example.com/a?id=1#top");
+        assert_eq!(normalize("https://example.com/a?q=a%20b&gclid=1"), "https://example.com/a?q=a+b");
+        assert_eq!(normalize("https://example.com/a?q=a%20b"), "https://example.com/a?q=a%20b");
+    }
+
+    #[test]
+    fn test_unwrap_redirects() {
+        assert_eq!(
+            normalize("https://l.facebook.com/l.php?u=https%3A%2F%2Fexample.com%2F%3Futm_source%3Dfb&h=AT0"),
+            "https://example.com/"
+        );
+        assert_eq!(
+            normalize("https://www.google.co.uk/url?sa=t&q=https://example.com/page&usg=x"),
+            "https://example.com/page"
+        );
+        assert_eq!(
+            normalize("https://www.google.com/url?q=javascript:alert(1)"),
+            "https://www.google.com/url?q=javascript:alert(1)"
+        );
+        assert_eq!(normalize("https://www.google.com/search?q=rust"), "https://www.google.com/search?q=rust");
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let mut normalizer = LinkNormalizer::new();
+        normalizer.block_param("ref").unwrap_redirect("out.example.com", "/go", "to");
+
+        let url = Url::parse("https://out.example.com/go?to=https%3A%2F%2Fexample.org%2F%3Fref%3Dx%26utm_id%3D1").unwrap();
+        assert_eq!(normalizer.normalize(&url).as_str(), "https://example.org/?utm_id=1");
+
+        normalizer.allow_param("ref");
+        assert_eq!(normalizer.normalize(&url).as_str(), "https://example.org/?ref=x&utm_id=1");
+    }
+}
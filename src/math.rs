@@ -0,0 +1,254 @@
+use std::iter;
+
+use html5ever::{LocalName, QualName, local_name, namespace_url, ns};
+use kuchiki::{ElementData, NodeDataRef, NodeRef};
+use kuchiki::iter::NodeIterator;
+use kuchiki::traits::TendrilSink;
+use lazy_static::lazy_static;
+use log::trace;
+use regex::Regex;
+
+use crate::sanitize::SAFE_MATHML_TAGS;
+
+
+lazy_static! {
+    // Output of MathJax 2, the source is kept in the following `<script>`.
+    static ref MATHJAX_OUTPUT: Regex = Regex::new(r"(?x)
+        \b(?:MathJax|MathJax_Display|MathJax_Preview|MathJax_SVG|MathJax_SVG_Display|MathJax_CHTML
+        |MJXc-display)\b
+    ").unwrap();
+
+    static ref KATEX: Regex = Regex::new(r"\bkatex(?:-display)?\b").unwrap();
+}
+
+
+/// Replaces rendered formulas of KaTeX and MathJax and their TeX sources with MathML.
+///
+/// TeX without MathML is kept as `<annotation encoding="application/x-tex">` of `<math>`.
+pub fn normalize(root: &NodeRef) {
+    for elem in find(root, |elem| &*elem.name.local == "mjx-container") {
+        let display = elem.attributes.borrow().get(local_name!("display")) == Some("true");
+        replace_with_math(elem.as_node(), display);
+    }
+
+    for elem in find(root, |elem| has_class(elem, &MATHJAX_OUTPUT)) {
+        replace_mathjax_output(&elem);
+    }
+
+    for elem in find(root, |elem| has_class(elem, &KATEX)) {
+        let display = elem.attributes.borrow().get(local_name!("class"))
+            .is_some_and(|class| class.split_whitespace().any(|class| class == "katex-display"));
+        replace_with_math(elem.as_node(), display);
+    }
+
+    for script in find(root, |elem| math_type(elem).is_some()) {
+        replace_script(&script);
+    }
+}
+
+/// Checks if the element is the root of a formula.
+pub fn is_math(elem: &ElementData) -> bool {
+    elem.name == mathml("math")
+}
+
+fn find<F>(root: &NodeRef, predicate: F) -> Vec<NodeDataRef<ElementData>>
+    where F: Fn(&ElementData) -> bool
+{
+    // Only outermost elements, inner ones are replaced along with them.
+    root.inclusive_descendants().elements()
+        .filter(|elem| predicate(elem))
+        .filter(|elem| !elem.as_node().ancestors().elements().any(|ancestor| predicate(&ancestor)))
+        .collect()
+}
+
+fn has_class(elem: &ElementData, pattern: &Regex) -> bool {
+    elem.attributes.borrow().get(local_name!("class")).is_some_and(|class| pattern.is_match(class))
+}
+
+fn mathml(name: &str) -> QualName {
+    QualName::new(None, ns!(mathml), LocalName::from(name))
+}
+
+// Returns the type of `<script type="math/tex">` or `<script type="math/mml">` and if it's displayed.
+fn math_type(elem: &ElementData) -> Option<(&'static str, bool)> {
+    if elem.name.local != local_name!("script") {
+        return None;
+    }
+
+    let attributes = elem.attributes.borrow();
+    let kind = attributes.get(local_name!("type"))?.to_ascii_lowercase();
+    let display = kind.contains("mode=display");
+
+    if kind.starts_with("math/tex") {
+        Some(("tex", display))
+    } else if kind.starts_with("math/mml") {
+        Some(("mml", display))
+    } else {
+        None
+    }
+}
+
+fn replace_mathjax_output(elem: &NodeDataRef<ElementData>) {
+    let node = elem.as_node();
+
+    // The source is preferred, because MathJax's output is just for display.
+    let has_source = node.following_siblings().elements()
+        .find(|sibling| !has_class(sibling, &MATHJAX_OUTPUT))
+        .is_some_and(|sibling| math_type(&sibling).is_some());
+
+    if has_source {
+        trace!("Removing the output of MathJax");
+        node.detach();
+    } else {
+        replace_with_math(node, false);
+    }
+}
+
+fn replace_with_math(node: &NodeRef, display: bool) {
+    let math = match node.descendants().elements().find(|elem| is_math(elem)) {
+        Some(math) => math,
+        None => return
+    };
+
+    if display {
+        math.attributes.borrow_mut().insert(local_name!("display"), "block".into());
+    }
+
+    trace!("Replacing the rendered formula with MathML");
+    node.insert_before(math.as_node().clone());
+    node.detach();
+}
+
+// Copies only MathML elements and their `display` and `encoding` attributes, other elements are dropped.
+fn copy_mathml(node: &NodeRef) -> Option<NodeRef> {
+    if let Some(text) = node.as_text() {
+        return Some(NodeRef::new_text(text.borrow().clone()));
+    }
+
+    let elem = node.as_element()?;
+
+    if elem.name.ns != ns!(mathml) || !SAFE_MATHML_TAGS.contains(&&*elem.name.local) {
+        return None;
+    }
+
+    let copy = NodeRef::new_element(elem.name.clone(), iter::empty());
+
+    {
+        let attributes = elem.attributes.borrow();
+        let mut copy_attributes = copy.as_element().unwrap().attributes.borrow_mut();
+
+        for name in [local_name!("display"), local_name!("encoding")] {
+            if let Some(value) = attributes.get(name.clone()) {
+                copy_attributes.insert(name, value.to_string());
+            }
+        }
+    }
+
+    for child in node.children() {
+        if let Some(child) = copy_mathml(&child) {
+            copy.append(child);
+        }
+    }
+
+    Some(copy)
+}
+
+fn replace_script(script: &NodeDataRef<ElementData>) {
+    let (kind, display) = math_type(script).unwrap();
+    let source = script.text_contents();
+
+    let math = if kind == "mml" {
+        let document = kuchiki::parse_html().one(source);
+        let math = document.descendants().elements().find(|elem| is_math(elem));
+
+        // The source is inert text, so it must not become arbitrary markup.
+        match math.and_then(|math| copy_mathml(math.as_node())) {
+            Some(math) => math,
+            None => return
+        }
+    } else {
+        let annotation = NodeRef::new_element(mathml("annotation"), iter::empty());
+        annotation.as_element().unwrap().attributes.borrow_mut()
+            .insert(local_name!("encoding"), "application/x-tex".into());
+        annotation.append(NodeRef::new_text(source.trim()));
+
+        let semantics = NodeRef::new_element(mathml("semantics"), iter::empty());
+        semantics.append(annotation);
+
+        let math = NodeRef::new_element(mathml("math"), iter::empty());
+        math.append(semantics);
+        math
+    };
+
+    if display {
+        math.as_element().unwrap().attributes.borrow_mut()
+            .insert(local_name!("display"), "block".into());
+    }
+
+    trace!("Replacing the source of the formula with MathML");
+    math.detach();
+    script.as_node().insert_before(math);
+    script.as_node().detach();
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+
+    fn normalized(html: &str) -> String {
+        let document = kuchiki::parse_html().one(format!("<div id=\"root\">{}</div>", html));
+        let root = document.select_first("#root").unwrap().as_node().clone();
+        normalize(&root);
+        root.children().map(|child| child.to_string()).collect()
+    }
+
+    #[test]
+    fn test_katex() {
+        assert_eq!(
+            normalized("<p>Let <span class=\"katex\"><span class=\"katex-mathml\"><math><semantics><mi>x</mi>\
+                <annotation encoding=\"application/x-tex\">x</annotation></semantics></math></span>\
+                <span class=\"katex-html\" aria-hidden=\"true\"><span class=\"mord mathnormal\">x</span></span></span>.</p>"),
+            "<p>Let <math><semantics><mi>x</mi><annotation encoding=\"application/x-tex\">x</annotation></semantics></math>.</p>"
+        );
+
+        assert_eq!(
+            normalized("<span class=\"katex-display\"><span class=\"katex\"><span class=\"katex-mathml\">\
+                <math><mi>y</mi></math></span><span class=\"katex-html\">y</span></span></span>"),
+            "<math display=\"block\"><mi>y</mi></math>"
+        );
+    }
+
+    #[test]
+    fn test_mathjax() {
+        assert_eq!(
+            normalized("<span class=\"MathJax_Preview\">x</span><span class=\"MathJax\" id=\"MathJax-Element-1-Frame\">\
+                <span class=\"math\">x</span></span><script type=\"math/tex\" id=\"MathJax-Element-1\">x^2</script>"),
+            "<math><semantics><annotation encoding=\"application/x-tex\">x^2</annotation></semantics></math>"
+        );
+
+        assert_eq!(
+            normalized("<script type=\"math/tex; mode=display\">\\sum_i i</script>"),
+            "<math display=\"block\"><semantics><annotation encoding=\"application/x-tex\">\\sum_i i</annotation></semantics></math>"
+        );
+
+        assert_eq!(
+            normalized("<script type=\"math/mml\"><math><mi>z</mi></math></script>"),
+            "<math><mi>z</mi></math>"
+        );
+
+        // Only MathML is taken from the inert source.
+        assert_eq!(
+            normalized("<script type=\"math/mml; mode=display\"><math display=\"block\" href=\"javascript:x\"><mtext onclick=\"x()\">a\
+                <b>b</b><img src=x onerror=alert(1)></mtext><maction><mi>c</mi></maction>\
+                <annotation-xml encoding=\"text/html\"><p>d</p></annotation-xml></math></script>"),
+            "<math display=\"block\"><mtext>a</mtext></math>"
+        );
+
+        assert_eq!(
+            normalized("<mjx-container class=\"MathJax\" jax=\"CHTML\" display=\"true\"><mjx-math>z</mjx-math>\
+                <mjx-assistive-mml><math display=\"block\"><mi>z</mi></math></mjx-assistive-mml></mjx-container>"),
+            "<math display=\"block\"><mi>z</mi></math>"
+        );
+    }
+}
@@ -1,6 +1,13 @@
+use std::time::Duration;
+
 use html5ever::local_name;
 use kuchiki::NodeRef;
 
+use crate::footnotes::Footnote;
+use crate::inventory::{Image, Link};
+use crate::language::{self, DetectedLanguage};
+use crate::toc::TocEntry;
+
 
 const TITLE_KEYS: [&str; 6] = [
     "og:title", "twitter:title", "dc:title", "dcterm:title",
@@ -17,14 +24,55 @@ const DESCRIPTION_KEYS: [&str; 7] = [
 const IMAGE_KEYS: [&str; 3] = [
     "og:image", "og:image:url", "twitter:image",
 ];
+const PUBLISHED_TIME_KEYS: [&str; 4] = [
+    "article:published_time", "og:article:published_time", "dc:date", "dcterm:created",
+];
+const SITE_NAME_KEYS: [&str; 2] = [
+    "og:site_name", "application-name",
+];
+
+// Separators between the article title and the site name or sections.
+const TITLE_SEPARATORS: [&str; 8] = [" | ", " - ", " – ", " — ", " \\ ", " / ", " > ", " » "];
+const HIERARCHICAL_SEPARATORS: [&str; 4] = [" \\ ", " / ", " > ", " » "];
 
 
+#[derive(Default)]
 pub struct Metadata {
+    /// The raw content of `<title>`.
     pub page_title: Option<String>,
+    /// The title without the site name and sections.
     pub article_title: Option<String>,
     pub image_url: Option<String>,
     pub byline: Option<String>,
     pub description: Option<String>,
+    /// The description is generated from the content instead of taken from meta tags.
+    pub is_description_generated: bool,
+    pub published_time: Option<String>,
+    pub site_name: Option<String>,
+    /// The number of words in the content, every CJK character is counted as a word.
+    pub word_count: u32,
+    /// The number of graphemes in the content, consecutive whitespaces are counted once.
+    pub char_count: u32,
+    /// The estimated time to read the content.
+    pub reading_time: Duration,
+    /// The language declared by `<html lang>` or `Content-Language`.
+    pub declared_lang: Option<String>,
+    /// The language detected from the content, requires the `language-detection` feature.
+    pub detected_lang: Option<DetectedLanguage>,
+    /// The best guess of the language as a BCP 47 tag.
+    pub lang: Option<String>,
+    /// The text direction, `ltr` or `rtl`, declared by the document or detected by the script.
+    pub dir: Option<String>,
+    /// The URL of the next page if the article is split into several pages.
+    pub next_page_url: Option<String>,
+    /// Links of the content to web pages, collected after `post_process` hooks and sanitizing.
+    pub links: Vec<Link>,
+    /// Images of the content, collected after `post_process` hooks and sanitizing.
+    pub images: Vec<Image>,
+    /// The table of contents built from headings of the content after `post_process` hooks and sanitizing.
+    pub toc: Vec<TocEntry>,
+    /// Footnotes and endnotes defined in the content, collected after `post_process` hooks and sanitizing.
+    pub footnotes: Vec<Footnote>,
 }
 
 
@@ -33,18 +81,42 @@ pub fn extract(root: &NodeRef) -> Metadata {
         .map(|node| node.text_contents())
         .ok();
 
+    let site_name = extract_meta_content(root, &SITE_NAME_KEYS)
+        .map(|name| normalize_spaces(&name))
+        .filter(|name| !name.is_empty());
+
     let mut article_title = get_article_title(root);
 
     match (&page_title, &article_title) {
         (None, Some(at)) => {page_title = Some(at.clone());},
-        (Some(pt), None) => {article_title = Some(pt.clone());},
+        (Some(pt), None) => {article_title = Some(clean_title(root, pt));},
         _ => (),
     }
 
+    if let (Some(title), Some(site_name)) = (&mut article_title, &site_name) {
+        *title = strip_site_name(title, site_name);
+    }
+
     let image_url = extract_meta_content(root, &IMAGE_KEYS);
     let byline = extract_meta_content(root, &BYLINE_KEYS);
-    let description = get_article_description(root);
-    Metadata {page_title, article_title, image_url, byline, description}
+    let description = extract_meta_content(root, &DESCRIPTION_KEYS);
+    let published_time = extract_meta_content(root, &PUBLISHED_TIME_KEYS);
+    Metadata {
+        page_title, article_title, image_url, byline, description, published_time, site_name,
+        is_description_generated: false,
+        word_count: 0,
+        char_count: 0,
+        reading_time: Duration::ZERO,
+        declared_lang: get_declared_lang(root),
+        detected_lang: None,
+        lang: None,
+        dir: None,
+        next_page_url: None,
+        links: Vec::new(),
+        images: Vec::new(),
+        toc: Vec::new(),
+        footnotes: Vec::new(),
+    }
 }
 
 
@@ -73,16 +145,166 @@ fn get_article_title(root: &NodeRef) -> Option<String> {
 }
 
 
-fn get_article_description(root: &NodeRef) -> Option<String> {
-    let meta_desc = extract_meta_content(root, &DESCRIPTION_KEYS);
-    if meta_desc.is_some() {
-        return meta_desc;
+fn get_declared_lang(root: &NodeRef) -> Option<String> {
+    let html_lang = root.select_first("html").ok()
+        .and_then(|html| html.attributes.borrow().get(local_name!("lang")).and_then(language::normalize_tag));
+
+    html_lang.or_else(|| {
+        root.select("meta[http-equiv][content]").unwrap()
+            .find(|meta| {
+                let attributes = meta.attributes.borrow();
+                attributes.get("http-equiv").unwrap().eq_ignore_ascii_case("content-language")
+            })
+            .and_then(|meta| language::normalize_tag(meta.attributes.borrow().get(local_name!("content")).unwrap()))
+    })
+}
+
+// Removes the site name and sections from the title in the same way as mozilla/readability.
+fn clean_title(root: &NodeRef, title: &str) -> String {
+    let original = normalize_spaces(title);
+    let mut title = original.clone();
+    let mut had_hierarchical_separators = false;
+
+    let last_separator = TITLE_SEPARATORS.iter()
+        .filter_map(|sep| original.rfind(sep).map(|index| (index, *sep)))
+        .max_by_key(|(index, _)| *index);
+
+    if let Some((index, _)) = last_separator {
+        had_hierarchical_separators = HIERARCHICAL_SEPARATORS.iter().any(|sep| original.contains(sep));
+
+        // Remove the last part, unless it leaves too few words, then remove the first one instead.
+        title = original[..index].to_string();
+
+        if word_count(&title) < 3 {
+            let (index, sep) = TITLE_SEPARATORS.iter()
+                .filter_map(|sep| original.find(sep).map(|index| (index, *sep)))
+                .min_by_key(|(index, _)| *index)
+                .unwrap();
+
+            title = original[index + sep.len()..].to_string();
+        }
+    } else if original.contains(": ") {
+        // Check if the page has a heading with exactly this title, otherwise remove the prefix.
+        let has_heading = root.select("h1, h2").unwrap()
+            .any(|heading| normalize_spaces(&heading.text_contents()) == original);
+
+        if !has_heading {
+            let (first, _) = original.split_once(':').unwrap();
+            let (_, last) = original.rsplit_once(':').unwrap();
+
+            if word_count(last) >= 3 {
+                if word_count(first) <= 5 {
+                    title = last.to_string();
+                }
+            } else {
+                title = original[first.len() + 1..].to_string();
+            }
+        }
+    } else if original.chars().count() > 150 || original.chars().count() < 15 {
+        let mut h1s = root.select("h1").unwrap();
+
+        if let (Some(h1), None) = (h1s.next(), h1s.next()) {
+            title = h1.text_contents();
+        }
     }
 
-    // if the description isn't specified in a meta tag, use the text of the first <p>
-    root.select_first("p")
-        .map(|p| p.text_contents())
-        .ok()
+    let title = normalize_spaces(&title);
+    let words = word_count(&title);
+
+    // Short titles are likely to be the site name or the section, unless the separators are
+    // hierarchical and only the first part has been removed.
+    let separators = original.split_whitespace().filter(|word| is_separator(word)).count();
+
+    if words <= 4 && (!had_hierarchical_separators || words + 1 != word_count(&original) - separators) {
+        return original;
+    }
+
+    title
+}
+
+// Removes the site name if the title starts or ends with it.
+fn strip_site_name(title: &str, site_name: &str) -> String {
+    for sep in TITLE_SEPARATORS.iter().chain(&[": "]) {
+        let stripped = title.strip_suffix(site_name).and_then(|rest| rest.trim_end().strip_suffix(sep.trim_end()))
+            .or_else(|| title.strip_prefix(site_name).and_then(|rest| rest.trim_start().strip_prefix(sep.trim_start())));
+
+        if let Some(stripped) = stripped.map(str::trim).filter(|stripped| !stripped.is_empty()) {
+            return stripped.to_string();
+        }
+    }
+
+    title.to_string()
+}
+
+fn is_separator(word: &str) -> bool {
+    TITLE_SEPARATORS.iter().any(|sep| sep.trim() == word)
+}
+
+fn word_count(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+fn normalize_spaces(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+impl Metadata {
+    // Descriptions that are empty or repeat the title say nothing about the content.
+    pub(crate) fn has_poor_description(&self) -> bool {
+        match self.description {
+            Some(ref description) => {
+                let description = normalize_spaces(description);
+                description.is_empty() || self.article_title.as_ref() == Some(&description)
+            },
+            None => true
+        }
+    }
+}
+
+
+/// Returns the first paragraph long enough to describe the content, truncated to `max_len` chars.
+pub fn generate_excerpt(content: &NodeRef, min_len: usize, max_len: usize) -> Option<String> {
+    content.select("p").unwrap()
+        .map(|p| normalize_spaces(&p.text_contents()))
+        .find(|text| text.chars().count() >= min_len)
+        .map(|text| truncate(&text, max_len))
+}
+
+// Cuts the text at the end of a sentence if it's not too short, otherwise at a word boundary.
+fn truncate(text: &str, max_len: usize) -> String {
+    let end = match text.char_indices().nth(max_len) {
+        Some((end, _)) => end,
+        None => return text.to_string()
+    };
+
+    let head = &text[..end];
+    let min_sentence_end = head.len() / 2;
+
+    let sentence_end = head.char_indices().rev()
+        .filter(|&(index, c)| match c {
+            '.' | '!' | '?' => text[index + c.len_utf8()..].starts_with(char::is_whitespace),
+            '。' | '！' | '？' => true,
+            _ => false,
+        })
+        .map(|(index, c)| index + c.len_utf8())
+        .next()
+        .filter(|&index| index > min_sentence_end);
+
+    if let Some(index) = sentence_end {
+        return text[..index].to_string();
+    }
+
+    // Leave a place for the ellipsis.
+    let end = head.char_indices().last().map_or(0, |(index, _)| index);
+    let head = &text[..end];
+
+    // Don't leave a part of a word.
+    let head = match text[end..].starts_with(char::is_whitespace) {
+        true => head,
+        false => head.rfind(char::is_whitespace).map_or(head, |index| &head[..index])
+    };
+
+    format!("{}…", head.trim_end_matches(|c: char| c.is_whitespace() || c.is_ascii_punctuation()))
 }
 
 
@@ -114,7 +336,7 @@ fn extract_meta_content(root: &NodeRef, expected_types: &[&str]) -> Option<Strin
 mod tests {
     #![cfg(test)]
     use super::*;
-    use kuchiki::{parse_html, traits::TendrilSink};
+    use kuchiki::traits::TendrilSink;
 
     #[test]
     fn test_extract() {
@@ -138,4 +360,31 @@ mod tests {
         assert_eq!(metadata.byline, Some("Joe Schmoe".into()));
         assert_eq!(metadata.description, Some("A test article for test cases.".into()));
     }
+
+    #[test]
+    fn test_clean_title() {
+        let root = kuchiki::parse_html().one("<h1>Breaking: Something has happened today</h1>");
+        let clean = |title| clean_title(&root, title);
+
+        assert_eq!(clean("Some Long Article Title Here - Some Site"), "Some Long Article Title Here");
+        assert_eq!(clean("Site | Some Long Article Title Here"), "Some Long Article Title Here");
+        assert_eq!(clean("Short Title - Some Site"), "Short Title - Some Site");
+        assert_eq!(clean("Site » Section » Another Article"), "Site » Section");
+        assert_eq!(clean("Breaking: Something has happened today"), "Breaking: Something has happened today");
+        assert_eq!(clean("News: Something else has happened today"), "Something else has happened today");
+        assert_eq!(clean("Tiny"), "Breaking: Something has happened today");
+
+        assert_eq!(strip_site_name("Some Article - Some Site", "Some Site"), "Some Article");
+        assert_eq!(strip_site_name("Some Site: Some Article", "Some Site"), "Some Article");
+        assert_eq!(strip_site_name("Some Site", "Some Site"), "Some Site");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("Short text.", 20), "Short text.");
+        assert_eq!(truncate("First sentence here. Second one is longer.", 30), "First sentence here.");
+        assert_eq!(truncate("A sentence. Followed by a long sentence without end", 30), "A sentence. Followed by a…");
+        assert_eq!(truncate("Word, another, and a third", 16), "Word, another…");
+        assert_eq!(truncate("第一句话。第二句话很长很长", 8), "第一句话。");
+    }
 }
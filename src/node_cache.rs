@@ -36,6 +36,6 @@ impl<T: Default> NodeCache<T> {
 
     pub fn get_or_create(&mut self, node: &NodeRef) -> &mut T {
         let key = HashableNodeRef(node.clone());
-        self.0.entry(key).or_insert_with(Default::default)
+        self.0.entry(key).or_default()
     }
 }
@@ -0,0 +1,324 @@
+use std::cmp;
+use std::collections::HashMap;
+
+use html5ever::local_name;
+use kuchiki::NodeRef;
+use kuchiki::iter::NodeIterator;
+use lazy_static::lazy_static;
+use regex::Regex;
+use url::Url;
+
+use crate::heuristics::Heuristics;
+
+
+lazy_static! {
+    static ref EXTRANEOUS: Regex = Regex::new(r"(?xi)
+        print|archive|comment|discuss|e[-]?mail|share|reply|all|login|sign|single
+    ").unwrap();
+
+    static ref NEXT_LINK: Regex = Regex::new(r"(?xi)
+        next|weiter|continue|>([^|]|$)|»([^|]|$)
+    ").unwrap();
+
+    static ref PREV_LINK: Regex = Regex::new(r"(?xi)
+        prev|earl|old|new|<|«
+    ").unwrap();
+
+    static ref FIRST_OR_LAST: Regex = Regex::new(r"(?i)first|last").unwrap();
+    static ref PAGINATION: Regex = Regex::new(r"(?i)pag(e|ing|inat)").unwrap();
+    static ref PAGE_IN_URL: Regex = Regex::new(r"(?i)p(a|g|ag)?(e|ing|ination)?(=|/)[0-9]{1,2}").unwrap();
+    static ref PAGE_SUFFIX: Regex = Regex::new(r"(?i)((_|-)?p[a-z]*|_|-)[0-9]{1,2}$").unwrap();
+    static ref PAGE_NUMBER: Regex = Regex::new(r"^[0-9]{1,2}$").unwrap();
+    static ref NUMBER: Regex = Regex::new(r"[0-9]+").unwrap();
+}
+
+// Links scoring less are never considered to be the next page.
+const MIN_SCORE: i32 = 50;
+
+
+/// Finds a link to the next page of the article.
+///
+/// An explicit `<link rel="next">` wins, otherwise links are scored in the same way as
+/// Arc90's `findNextPageLink` does: by their text, classes of the link and its ancestors
+/// and similarity of the URL to the URL of the page.
+pub fn find_next_page(root: &NodeRef, page_url: &Url, heuristics: &Heuristics) -> Option<Url> {
+    let current = normalize_url(page_url.as_str());
+    let resolve = |href: &str| page_url.join(href.trim()).ok()
+        .filter(|url| url.host_str() == page_url.host_str())
+        .filter(|url| normalize_url(url.as_str()) != current);
+
+    let article_base = article_base_url(page_url);
+
+    // Sites also use `rel="next"` for the next article, so the URL must be similar.
+    let link = root.select("link[rel~=next][href]").unwrap()
+        .filter_map(|link| resolve(link.attributes.borrow().get(local_name!("href")).unwrap()))
+        .find(|url| {
+            let href = normalize_url(url.as_str());
+            href.starts_with(&article_base) || is_increment(&current, &href)
+        });
+
+    if link.is_some() {
+        return link;
+    }
+    let mut scores: HashMap<String, (i32, Url)> = HashMap::new();
+
+    for link in root.select("a[href]").unwrap() {
+        let attributes = link.attributes.borrow();
+        let url = match resolve(attributes.get(local_name!("href")).unwrap()) {
+            Some(url) => url,
+            None => continue
+        };
+
+        let href = normalize_url(url.as_str());
+
+        if href == article_base {
+            continue;
+        }
+
+        let text = link.text_contents();
+        let text = text.trim();
+
+        if EXTRANEOUS.is_match(text) || text.chars().count() > 25 {
+            continue;
+        }
+
+        // The next page must differ from the article by a number.
+        if !href.replacen(&article_base, "", 1).chars().any(|c| c.is_ascii_digit()) {
+            continue;
+        }
+
+        if is_decrement(&current, &href) {
+            continue;
+        }
+
+        let is_next = attributes.get(local_name!("rel"))
+            .is_some_and(|rel| rel.split_whitespace().any(|r| r == "next"));
+        let is_increment = is_increment(&current, &href);
+
+        // Unlike Arc90, require at least one strong signal to avoid links like "Cite this page".
+        if !(is_next || is_increment || NEXT_LINK.is_match(text) || text.parse::<u32>().is_ok()) {
+            continue;
+        }
+
+        // Arc90 only penalizes other URLs, but that lets through links to the next article.
+        if !href.starts_with(&article_base) && !is_increment {
+            continue;
+        }
+
+        let mut score = 0;
+
+        let class = attributes.get(local_name!("class")).unwrap_or("");
+        let id = attributes.get(local_name!("id")).unwrap_or("");
+        let data = format!("{} {} {}", text, class, id);
+
+        if NEXT_LINK.is_match(&data) {
+            score += 50;
+        }
+
+        if is_next {
+            score += 100;
+        }
+
+        if PAGINATION.is_match(&data) {
+            score += 25;
+        }
+
+        if FIRST_OR_LAST.is_match(&data) && !NEXT_LINK.is_match(text) {
+            score -= 65;
+        }
+
+        if heuristics.negative.is_match(&data) || EXTRANEOUS.is_match(&data) {
+            score -= 50;
+        }
+
+        if PREV_LINK.is_match(&data) {
+            score -= 200;
+        }
+
+        let mut positive_parent = false;
+        let mut negative_parent = false;
+
+        for ancestor in link.as_node().ancestors().elements() {
+            let attributes = ancestor.attributes.borrow();
+            let class = attributes.get(local_name!("class")).unwrap_or("");
+            let id = attributes.get(local_name!("id")).unwrap_or("");
+            let class_and_id = format!("{} {}", class, id);
+
+            if !positive_parent && PAGINATION.is_match(&class_and_id) {
+                score += 25;
+                positive_parent = true;
+            }
+
+            if !negative_parent && heuristics.negative.is_match(&class_and_id)
+                && !heuristics.positive.is_match(&class_and_id)
+            {
+                score -= 25;
+                negative_parent = true;
+            }
+        }
+
+        if PAGE_IN_URL.is_match(&href) || PAGINATION.is_match(&href) {
+            score += 25;
+        }
+
+        if EXTRANEOUS.is_match(&href) {
+            score -= 15;
+        }
+
+        if is_increment {
+            score += 50;
+        }
+
+        if let Ok(number) = text.parse::<i32>() {
+            score += if number == 1 { -10 } else { cmp::max(0, 10 - number) };
+        }
+
+        scores.entry(href).or_insert((0, url)).0 += score;
+    }
+
+    scores.into_values()
+        .filter(|(score, _)| *score >= MIN_SCORE)
+        .max_by_key(|(score, url)| (*score, cmp::Reverse(url.to_string())))
+        .map(|(_, url)| url)
+}
+
+// Strips the fragment and trailing slashes, so that the same page is compared equal.
+fn normalize_url(url: &str) -> String {
+    let url = url.split('#').next().unwrap();
+    url.trim_end_matches('/').to_string()
+}
+
+// Strips page numbers from the URL to get the URL of the whole article, as Arc90's `findBaseUrl`.
+fn article_base_url(url: &Url) -> String {
+    let mut base = url.clone();
+    base.set_query(None);
+    base.set_fragment(None);
+
+    let segments = base.path_segments()
+        .map(|segments| segments.filter(|s| !s.is_empty()).map(String::from).collect::<Vec<_>>())
+        .unwrap_or_default();
+
+    let count = segments.len();
+    let mut cleaned = Vec::with_capacity(count);
+
+    for (index, segment) in segments.into_iter().enumerate() {
+        let from_end = count - index - 1;
+
+        if from_end < 2 && PAGE_NUMBER.is_match(&segment) {
+            continue;
+        }
+
+        if from_end == 0 && segment.eq_ignore_ascii_case("index") {
+            continue;
+        }
+
+        // Extensions are dropped, so that `story-p2.html` is related to `story.html`.
+        let segment = match segment.rsplit_once('.') {
+            Some((name, ext)) if from_end == 0 && ext.chars().all(|c| c.is_ascii_alphabetic()) => name.to_string(),
+            _ => segment
+        };
+
+        let segment = if from_end < 2 {
+            PAGE_SUFFIX.replace(&segment, "").into_owned()
+        } else {
+            segment
+        };
+
+        if !segment.is_empty() {
+            cleaned.push(segment);
+        }
+    }
+
+    base.set_path(&cleaned.join("/"));
+    normalize_url(base.as_str())
+}
+
+// Checks if the URLs are the same except for a number increased by one.
+fn is_increment(current: &str, next: &str) -> bool {
+    changed_number(current, next).is_some_and(|(a, b)| a.checked_add(1) == Some(b))
+}
+
+// Checks if the URLs are the same except for a decreased number, e.g. links to previous pages.
+fn is_decrement(current: &str, next: &str) -> bool {
+    changed_number(current, next).is_some_and(|(a, b)| b < a)
+}
+
+// Returns the only number that differs in the URLs.
+fn changed_number(current: &str, next: &str) -> Option<(u64, u64)> {
+    if NUMBER.replace_all(current, "#") != NUMBER.replace_all(next, "#") {
+        return None;
+    }
+
+    let mut changed = NUMBER.find_iter(current).zip(NUMBER.find_iter(next))
+        .filter(|(a, b)| a.as_str() != b.as_str());
+
+    match (changed.next(), changed.next()) {
+        (Some((a, b)), None) => a.as_str().parse().ok().zip(b.as_str().parse().ok()),
+        _ => None
+    }
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+
+    fn find(html: &str, url: &str) -> Option<String> {
+        let root = kuchiki::parse_html().one(html);
+        let url = Url::parse(url).unwrap();
+        find_next_page(&root, &url, &Heuristics::default()).map(String::from)
+    }
+
+    #[test]
+    fn test_article_base_url() {
+        let base = |url| article_base_url(&Url::parse(url).unwrap());
+        assert_eq!(base("http://example.com/news/story/2?utm=1"), "http://example.com/news/story");
+        assert_eq!(base("http://example.com/news/story-p2.html"), "http://example.com/news/story");
+        assert_eq!(base("http://example.com/news/story/index"), "http://example.com/news/story");
+        assert_eq!(base("http://example.com/2020/story"), "http://example.com/2020/story");
+    }
+
+    #[test]
+    fn test_is_increment() {
+        assert!(is_increment("http://a.com/story?page=2", "http://a.com/story?page=3"));
+        assert!(is_increment("http://a.com/2020/story/9", "http://a.com/2020/story/10"));
+        assert!(!is_increment("http://a.com/story?page=2", "http://a.com/story?page=1"));
+        assert!(!is_increment("http://a.com/2020/story/1", "http://a.com/2021/story/2"));
+    }
+
+    #[test]
+    fn test_find_next_page() {
+        let html = "<article><p>Text</p></article>
+            <div class=\"pagination\">
+                <a href=\"/story?page=1\">1</a>
+                <a href=\"/story?page=2\">2</a>
+                <a href=\"/story?page=3\">3</a>
+                <a href=\"/story?page=3\">Next »</a>
+                <a href=\"/story?page=1\">« Prev</a>
+                <a href=\"/story/comments?page=3\">Comments</a>
+            </div>";
+
+        assert_eq!(find(html, "http://example.com/story?page=2").as_deref(),
+                   Some("http://example.com/story?page=3"));
+
+        // Links to previous pages are ignored on the last page.
+        assert_eq!(find(html, "http://example.com/story?page=3"), None);
+
+        let html = "<article><p>Text</p></article>
+            <a href=\"/about/team2\">Team</a>
+            <a href=\"http://other.com/story?page=2\">Next</a>";
+
+        assert_eq!(find(html, "http://example.com/story"), None);
+
+        let html = "<head><link rel=\"next\" href=\"/story/2\"></head><body></body>";
+        assert_eq!(find(html, "http://example.com/story").as_deref(), Some("http://example.com/story/2"));
+
+        let html = "<head><link rel=\"next\" href=\"/another-story\"></head><body></body>";
+        assert_eq!(find(html, "http://example.com/story"), None);
+
+        let html = "<p><a rel=\"next\" href=\"/story/part-two-2\">Read on</a></p>";
+        assert_eq!(find(html, "http://example.com/story").as_deref(),
+                   Some("http://example.com/story/part-two-2"));
+    }
+}
@@ -0,0 +1,262 @@
+use html5ever::{LocalName, local_name, namespace_url, ns};
+use kuchiki::{Attributes, ElementData, NodeRef};
+use kuchiki::iter::NodeIterator;
+use log::trace;
+
+use crate::heuristics::PatternList;
+
+
+// Elements removed along with their content.
+// `<annotation-xml>` can contain arbitrary HTML.
+const DANGEROUS_TAGS: [&str; 22] = [
+    "script", "style", "noscript", "template", "object", "embed", "applet", "param", "base",
+    "meta", "link", "title", "frame", "frameset", "form", "input", "button", "select", "textarea",
+    "option", "svg", "annotation-xml",
+];
+
+// Elements kept as is, other ones are replaced with their content.
+const SAFE_TAGS: [&str; 78] = [
+    "a", "abbr", "address", "article", "aside", "audio", "b", "bdi", "bdo", "blockquote", "br",
+    "caption", "center", "cite", "code", "col", "colgroup", "data", "dd", "del", "details", "dfn",
+    "div", "dl", "dt", "em", "figcaption", "figure", "footer", "h1", "h2", "h3", "h4", "h5", "h6",
+    "header", "hr", "i", "iframe", "img", "ins", "kbd", "li", "main", "mark", "nav", "ol", "p",
+    "picture", "pre", "q", "rp", "rt", "ruby", "s", "samp", "section", "small", "source", "span",
+    "strike", "strong", "sub", "summary", "sup", "table", "tbody", "td", "tfoot", "th", "thead",
+    "time", "tr", "track", "u", "ul", "var", "video",
+];
+
+// Presentation MathML, `<maction>` and others are replaced with their content.
+pub(crate) const SAFE_MATHML_TAGS: [&str; 31] = [
+    "math", "semantics", "annotation", "mrow", "mi", "mn", "mo", "mtext", "mspace", "ms",
+    "mfrac", "msqrt", "mroot", "mstyle", "merror", "mpadded", "mphantom", "menclose", "msub",
+    "msup", "msubsup", "munder", "mover", "munderover", "mmultiscripts", "mprescripts", "none",
+    "mtable", "mtr", "mtd", "mlabeledtr",
+];
+
+// Attributes containing URLs, their schemes are checked.
+const URL_ATTRS: [&str; 9] = [
+    "href", "src", "srcset", "poster", "cite", "action", "formaction", "background", "data",
+];
+
+// Attributes that can execute scripts or change the page regardless of their values.
+const DANGEROUS_ATTRS: [&str; 4] = ["style", "srcdoc", "http-equiv", "formaction"];
+
+const SAFE_SCHEMES: [&str; 4] = ["http", "https", "mailto", "tel"];
+
+// Images can be embedded, but not SVG ones, which can contain scripts.
+const SAFE_DATA_TYPES: [&str; 5] = [
+    "data:image/png", "data:image/jpeg", "data:image/gif", "data:image/webp", "data:image/avif",
+];
+
+
+/// Enforces a safe policy of tags, attributes and URL schemes on the tree.
+///
+/// The root is never removed, only its attributes are cleaned.
+/// Frames are kept only if they load a page matching `allowed_embeds`.
+pub fn sanitize(root: &NodeRef, allowed_embeds: &PatternList) {
+    let elements = root.inclusive_descendants().elements().collect::<Vec<_>>();
+
+    for elem in elements {
+        let node = elem.as_node();
+        let is_root = node == root;
+
+        // Skip elements removed along with their ancestors.
+        if !is_root && !node.ancestors().any(|ancestor| ancestor == *root) {
+            continue;
+        }
+
+        if !is_root && is_dangerous(&elem, allowed_embeds) {
+            trace!("Removing unsafe <{}>", elem.name.local);
+            node.detach();
+            continue;
+        }
+
+        if !is_root && !is_safe(&elem) {
+            trace!("Unwrapping unsafe <{}>", elem.name.local);
+
+            for child in node.children().collect::<Vec<_>>() {
+                node.insert_before(child);
+            }
+
+            node.detach();
+            continue;
+        }
+
+        let mut attributes = elem.attributes.borrow_mut();
+        clean_attributes(&elem.name.local, &mut attributes);
+
+        if elem.name.local == local_name!("a") && attributes.contains(local_name!("href")) {
+            add_rel(&mut attributes);
+        }
+    }
+}
+
+fn is_dangerous(elem: &ElementData, allowed_embeds: &PatternList) -> bool {
+    if DANGEROUS_TAGS.contains(&&*elem.name.local) {
+        return true;
+    }
+
+    elem.name.local == local_name!("iframe") && !elem.attributes.borrow().get(local_name!("src"))
+        .is_some_and(|src| matches!(scheme(src).as_deref(), Some("http" | "https")) && allowed_embeds.is_match(src))
+}
+
+fn is_safe(elem: &ElementData) -> bool {
+    match elem.name.ns {
+        ns!(html) => SAFE_TAGS.contains(&&*elem.name.local),
+        ns!(mathml) => SAFE_MATHML_TAGS.contains(&&*elem.name.local),
+        _ => false
+    }
+}
+
+fn clean_attributes(tag: &LocalName, attributes: &mut Attributes) {
+    attributes.map.retain(|name, attr| {
+        let local = &*name.local;
+
+        // Namespaced attributes like `xlink:href` are useless in HTML.
+        if name.ns != ns!() || local.starts_with("on") || DANGEROUS_ATTRS.contains(&local) {
+            return false;
+        }
+
+        if !URL_ATTRS.contains(&local) {
+            return true;
+        }
+
+        if local == "srcset" {
+            return attr.value.split(',')
+                .filter_map(|candidate| candidate.split_whitespace().next())
+                .all(|url| is_safe_url(url, false));
+        }
+
+        is_safe_url(&attr.value, *tag == local_name!("img") && local == "src")
+    });
+}
+
+fn is_safe_url(url: &str, allow_data: bool) -> bool {
+    match scheme(url).as_deref() {
+        None => true,
+        Some("data") if allow_data => {
+            let url = url.trim().to_ascii_lowercase();
+            SAFE_DATA_TYPES.iter().any(|prefix| url.starts_with(prefix))
+        },
+        Some(scheme) => SAFE_SCHEMES.contains(&scheme),
+    }
+}
+
+// Returns the lowercased scheme of the URL, ignoring whitespaces and control chars like browsers do.
+fn scheme(url: &str) -> Option<String> {
+    let url = url.chars()
+        .filter(|c| !c.is_whitespace() && !c.is_control())
+        .collect::<String>()
+        .to_ascii_lowercase();
+
+    let end = url.find([':', '/', '?', '#'])?;
+
+    if url[end..].starts_with(':') {
+        Some(url[..end].to_string())
+    } else {
+        None
+    }
+}
+
+fn add_rel(attributes: &mut Attributes) {
+    let mut rel = attributes.get(local_name!("rel")).unwrap_or("")
+        .split_whitespace()
+        .map(str::to_string)
+        .collect::<Vec<_>>();
+
+    for value in ["noopener", "noreferrer"] {
+        if !rel.iter().any(|r| r.eq_ignore_ascii_case(value)) {
+            rel.push(value.to_string());
+        }
+    }
+
+    attributes.insert(local_name!("rel"), rel.join(" "));
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+    use crate::heuristics::Heuristics;
+
+    fn sanitized(html: &str) -> String {
+        let document = kuchiki::parse_html().one(format!("<div id=\"root\">{}</div>", html));
+        let root = document.select_first("#root").unwrap().as_node().clone();
+        sanitize(&root, &Heuristics::default().video);
+        root.to_string()
+    }
+
+    #[test]
+    fn test_xss_vectors() {
+        const VECTORS: &[&str] = &[
+            "<script>alert(1)</script>",
+            "<SCRIPT SRC=//xss.example/x.js></SCRIPT>",
+            "<img src=x onerror=alert(1)>",
+            "<img src=\"javascript:alert(1)\">",
+            "<img src=\"jav&#x09;ascript:alert(1)\">",
+            "<img src=\" &#14;  javascript:alert(1)\">",
+            "<img srcset=\"a.png 1x, javascript:alert(1) 2x\">",
+            "<img src=\"data:image/svg+xml;base64,PHN2ZyBvbmxvYWQ9YWxlcnQoMSk+\">",
+            "<a href=\"javascript:alert(1)\">x</a>",
+            "<a href=\"JaVaScRiPt:alert(1)\">x</a>",
+            "<a href=\"vbscript:msgbox(1)\">x</a>",
+            "<a href=\"data:text/html;base64,PHNjcmlwdD5hbGVydCgxKTwvc2NyaXB0Pg==\">x</a>",
+            "<a href=\"&#106;avascript:alert(1)\">x</a>",
+            "<iframe srcdoc=\"<script>alert(1)</script>\"></iframe>",
+            "<iframe src=\"javascript:alert(1)\"></iframe>",
+            "<iframe src=\"https://evil.example/x\"></iframe>",
+            "<svg onload=alert(1)><script>alert(1)</script></svg>",
+            "<svg><a xlink:href=\"javascript:alert(1)\"><text>x</text></a></svg>",
+            "<math><maction actiontype=statusline xlink:href=javascript:alert(1)>x</maction></math>",
+            "<math><mi href=\"javascript:alert(1)\">x</mi></math>",
+            "<math><annotation-xml encoding=\"text/html\"><img src=x onerror=alert(1)></annotation-xml></math>",
+            "<meta http-equiv=\"refresh\" content=\"0;url=javascript:alert(1)\">",
+            "<base href=\"javascript:alert(1)//\">",
+            "<link rel=stylesheet href=\"javascript:alert(1)\">",
+            "<style>@import 'javascript:alert(1)';</style>",
+            "<div style=\"background:url(javascript:alert(1))\">x</div>",
+            "<object data=\"javascript:alert(1)\"></object>",
+            "<embed src=\"javascript:alert(1)\">",
+            "<form action=\"javascript:alert(1)\"><button>x</button></form>",
+            "<button formaction=\"javascript:alert(1)\">x</button>",
+            "<video poster=\"javascript:alert(1)\" onerror=alert(1)></video>",
+            "<body onload=alert(1)>",
+            "<details open ontoggle=alert(1)>",
+            "<marquee onstart=alert(1)>x</marquee>",
+            "<blockquote cite=\"javascript:alert(1)\">x</blockquote>",
+            "<table background=\"javascript:alert(1)\"><tr><td>x</td></tr></table>",
+            "<noscript><p title=\"</noscript><img src=x onerror=alert(1)>\"></noscript>",
+            "<template><script>alert(1)</script></template>",
+        ];
+
+        for vector in VECTORS {
+            let output = sanitized(vector).to_lowercase();
+
+            for needle in ["<script", "javascript:", "vbscript:", "data:text", "data:image/svg", "onerror",
+                           "onload", "ontoggle", "onstart", "srcdoc", "<svg", "<maction", "<annotation-xml",
+                           "<meta", "<base", "<link", "<style", "style=", "<object", "<embed", "<form",
+                           "formaction", "evil.example"] {
+                assert!(!output.contains(needle), "{} => {}", vector, output);
+            }
+        }
+    }
+
+    #[test]
+    fn test_safe_content() {
+        assert_eq!(
+            sanitized("<p>Text <a href=\"https://example.com\" rel=\"nofollow\">link</a></p>"),
+            "<div id=\"root\"><p>Text <a href=\"https://example.com\" rel=\"nofollow noopener noreferrer\">link</a></p></div>"
+        );
+
+        assert_eq!(
+            sanitized("<p><marquee>Text</marquee> <img src=\"data:image/png;base64,AA==\" alt=\"x\"></p>"),
+            "<div id=\"root\"><p>Text <img alt=\"x\" src=\"data:image/png;base64,AA==\"></p></div>"
+        );
+
+        assert_eq!(
+            sanitized("<iframe src=\"https://www.youtube.com/embed/x\"></iframe><a href=\"/x\">x</a>"),
+            "<div id=\"root\"><iframe src=\"https://www.youtube.com/embed/x\"></iframe><a href=\"/x\" rel=\"noopener noreferrer\">x</a></div>"
+        );
+    }
+}
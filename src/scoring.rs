@@ -0,0 +1,96 @@
+/// Weights and thresholds used to score and clean the content.
+///
+/// The default values are the ones used by mozilla/readability with minor changes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoringParams {
+    /// Initial score of `<section>`.
+    pub section_weight: f32,
+    /// Initial score of `<div>`.
+    pub div_weight: f32,
+    /// Initial score of `<pre>`, `<td>` and `<blockquote>`.
+    pub pre_weight: f32,
+    /// Initial score of `<address>` and `<form>`.
+    pub form_weight: f32,
+    /// Initial score of `<dl>`, `<dt>` and `<dd>`.
+    pub definition_list_weight: f32,
+    /// Initial score of `<li>`, `<ol>` and `<ul>`.
+    pub list_weight: f32,
+    /// Initial score of `<body>`.
+    pub body_weight: f32,
+    /// Initial score of `<h1>`-`<h6>`.
+    pub heading_weight: f32,
+    /// Initial score of `<th>`.
+    pub table_header_weight: f32,
+    /// Bonus (or penalty) for a positive (or negative) `class` or `id`.
+    pub class_weight: f32,
+
+    /// Paragraphs shorter than that aren't scored.
+    pub min_paragraph_len: u32,
+    /// A paragraph gets a point for every `chars_per_point` characters (at least one)...
+    pub chars_per_point: u32,
+    /// ... but no more than `max_length_points`.
+    pub max_length_points: u32,
+    /// Characters of scripts without word separators (CJK, Thai, ...) count as that many chars.
+    pub unspaced_char_weight: u32,
+    /// How many ancestors of a paragraph get its score.
+    pub ancestor_levels: usize,
+    /// Candidates scoring less than this share of the best score are discarded.
+    pub top_candidate_ratio: f32,
+    /// How many top candidates must share a parent to choose it instead of the best one.
+    pub min_candidates: usize,
+
+    /// Nodes having at least that many commas are always kept.
+    pub min_commas: u32,
+    /// Nodes with several images are removed if the ratio of paragraphs to images is lower.
+    pub min_p_img_ratio: f32,
+    /// Nodes are removed if they have more list items than paragraphs plus this value.
+    pub max_li_excess: u32,
+    /// Nodes are removed if the number of inputs multiplied by this value exceeds paragraphs.
+    pub input_ratio: u32,
+    /// Nodes with less text are removed unless they have some images...
+    pub min_text_len: u32,
+    /// ... but no more than `max_short_node_imgs`.
+    pub max_short_node_imgs: u32,
+    /// Maximum link density of nodes without a class bonus.
+    pub max_link_density: f32,
+    /// Maximum link density of nodes with a class bonus.
+    pub max_weighted_link_density: f32,
+    /// Nodes with a single embed are removed if they have less text.
+    pub min_embed_text_len: u32,
+}
+
+impl Default for ScoringParams {
+    fn default() -> Self {
+        ScoringParams {
+            section_weight: 15.,
+            div_weight: 5.,
+            pre_weight: 3.,
+            form_weight: -3.,
+            definition_list_weight: -3.,
+            list_weight: -3.,
+            body_weight: -5.,
+            heading_weight: -5.,
+            table_header_weight: -5.,
+            class_weight: 25.,
+
+            min_paragraph_len: 25,
+            chars_per_point: 100,
+            max_length_points: 3,
+            unspaced_char_weight: 3,
+            ancestor_levels: 3,
+            top_candidate_ratio: 0.75,
+            // TODO: mozilla/readability uses 3 here, but we still have problems.
+            min_candidates: 4,
+
+            min_commas: 10,
+            min_p_img_ratio: 0.5,
+            max_li_excess: 100,
+            input_ratio: 3,
+            min_text_len: 25,
+            max_short_node_imgs: 2,
+            max_link_density: 0.2,
+            max_weighted_link_density: 0.5,
+            min_embed_text_len: 75,
+        }
+    }
+}
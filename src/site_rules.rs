@@ -0,0 +1,307 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io;
+use std::iter;
+use std::path::Path;
+use std::str::FromStr;
+
+use html5ever::{QualName, local_name, namespace_url, ns};
+use kuchiki::{NodeRef, Selectors};
+
+
+/// Extraction rules for a specific site.
+///
+/// Rules can be parsed from a text format similar to the one used by FiveFilters site configs,
+/// but with CSS selectors instead of XPath:
+///
+/// ```text
+/// # Comments start with `#`.
+/// body: div.article-body
+/// title: h1.headline
+/// author: .byline a
+/// date: time[datetime]
+/// strip: .newsletter-signup
+/// strip_id_or_class: promo
+/// prefer_generic: no
+/// ```
+///
+/// Directives can be repeated, selectors are tried in order. Unknown directives are ignored.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SiteRule {
+    /// Selectors of the article body.
+    pub body: Vec<String>,
+    /// Selectors of the article title.
+    pub title: Vec<String>,
+    /// Selectors of the author.
+    pub author: Vec<String>,
+    /// Selectors of the publication date.
+    pub date: Vec<String>,
+    /// Selectors of elements removed from the document.
+    pub strip: Vec<String>,
+    /// Use the site rule only if the generic extraction fails.
+    pub prefer_generic: bool,
+}
+
+#[derive(Debug)]
+pub enum SiteRuleError {
+    Io(io::Error),
+    Syntax { line: usize, message: String },
+}
+
+impl fmt::Display for SiteRuleError {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SiteRuleError::Io(err) => write!(fmt, "cannot read site rule: {}", err),
+            SiteRuleError::Syntax { line, message } => write!(fmt, "line {}: {}", line, message),
+        }
+    }
+}
+
+impl Error for SiteRuleError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            SiteRuleError::Io(err) => Some(err),
+            SiteRuleError::Syntax { .. } => None,
+        }
+    }
+}
+
+impl From<io::Error> for SiteRuleError {
+    fn from(err: io::Error) -> Self {
+        SiteRuleError::Io(err)
+    }
+}
+
+impl FromStr for SiteRule {
+    type Err = SiteRuleError;
+
+    fn from_str(text: &str) -> Result<SiteRule, SiteRuleError> {
+        let mut rule = SiteRule::default();
+
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let error = |message: &str| SiteRuleError::Syntax {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let (key, value) = line.split_once(':').ok_or_else(|| error("expected `key: value`"))?;
+            let value = value.trim();
+
+            let list = match key.trim() {
+                "body" => &mut rule.body,
+                "title" => &mut rule.title,
+                "author" => &mut rule.author,
+                "date" => &mut rule.date,
+                "strip" => &mut rule.strip,
+                "strip_id_or_class" => {
+                    if value.is_empty() || value.contains(['"', '\\']) {
+                        return Err(error("invalid id or class"));
+                    }
+
+                    rule.strip.push(format!("[id*=\"{0}\"], [class*=\"{0}\"]", value));
+                    continue;
+                },
+                "prefer_generic" => {
+                    rule.prefer_generic = match value {
+                        "yes" | "true" => true,
+                        "no" | "false" => false,
+                        _ => return Err(error("expected `yes` or `no`")),
+                    };
+                    continue;
+                },
+                _ => continue
+            };
+
+            if Selectors::compile(value).is_err() {
+                return Err(error("invalid selector"));
+            }
+
+            list.push(value.to_string());
+        }
+
+        Ok(rule)
+    }
+}
+
+impl SiteRule {
+    pub(crate) fn strip(&self, root: &NodeRef) {
+        // Selectors are validated while parsing, but rules can also be built manually.
+        for selector in &self.strip {
+            let matches = root.select(selector).map_or_else(|_| Vec::new(), Iterator::collect);
+
+            for elem in matches {
+                elem.as_node().detach();
+            }
+        }
+    }
+
+    pub(crate) fn extract_title(&self, root: &NodeRef) -> Option<String> {
+        extract_text(root, &self.title)
+    }
+
+    pub(crate) fn extract_author(&self, root: &NodeRef) -> Option<String> {
+        extract_text(root, &self.author)
+    }
+
+    pub(crate) fn extract_date(&self, root: &NodeRef) -> Option<String> {
+        for selector in &self.date {
+            if let Ok(elem) = root.select_first(selector) {
+                let attributes = elem.attributes.borrow();
+
+                let date = attributes.get(local_name!("datetime"))
+                    .or_else(|| attributes.get(local_name!("content")))
+                    .map(|date| date.trim().to_string())
+                    .unwrap_or_else(|| elem.text_contents().trim().to_string());
+
+                if !date.is_empty() {
+                    return Some(date);
+                }
+            }
+        }
+
+        None
+    }
+
+    pub(crate) fn extract_body(&self, root: &NodeRef) -> Option<NodeRef> {
+        for selector in &self.body {
+            let mut matches = root.select(selector).map_or_else(|_| Vec::new(), Iterator::collect);
+
+            match matches.len() {
+                0 => continue,
+                1 => {
+                    let body = matches.pop().unwrap().as_node().clone();
+                    body.detach();
+                    return Some(body);
+                },
+                _ => {
+                    let name = QualName::new(None, ns!(html), local_name!("div"));
+                    let body = NodeRef::new_element(name, iter::empty());
+
+                    // Skip matches nested in other ones, they are moved along with their parents.
+                    for elem in &matches {
+                        let is_nested = elem.as_node().ancestors()
+                            .any(|ancestor| matches.iter().any(|other| *other.as_node() == ancestor));
+
+                        if !is_nested {
+                            body.append(elem.as_node().clone());
+                        }
+                    }
+
+                    return Some(body);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+fn extract_text(root: &NodeRef, selectors: &[String]) -> Option<String> {
+    selectors.iter()
+        .filter_map(|selector| root.select_first(selector).ok())
+        .map(|elem| elem.text_contents().trim().to_string())
+        .find(|text| !text.is_empty())
+}
+
+
+/// A set of site rules keyed by hostname.
+#[derive(Debug, Clone, Default)]
+pub struct SiteRules(HashMap<String, SiteRule>);
+
+impl SiteRules {
+    pub fn new() -> SiteRules {
+        SiteRules(HashMap::new())
+    }
+
+    /// Adds a rule for the host and all its subdomains.
+    pub fn add(&mut self, host: &str, rule: SiteRule) -> &mut Self {
+        self.0.insert(normalize_host(&host.to_lowercase()).to_string(), rule);
+        self
+    }
+
+    /// Loads a rule from the file named after the host, e.g. `example.com.txt`.
+    pub fn load_file<P: AsRef<Path>>(&mut self, path: P) -> Result<&mut Self, SiteRuleError> {
+        let path = path.as_ref();
+
+        let host = path.file_stem().and_then(|stem| stem.to_str())
+            .ok_or_else(|| SiteRuleError::Syntax {
+                line: 0,
+                message: format!("cannot get a host from {}", path.display()),
+            })?;
+
+        let rule = fs::read_to_string(path)?.parse()?;
+
+        Ok(self.add(host, rule))
+    }
+
+    /// Finds a rule for the host or the closest parent domain.
+    pub fn find(&self, host: &str) -> Option<&SiteRule> {
+        let host = host.to_lowercase();
+        let mut host = normalize_host(&host);
+
+        loop {
+            if let Some(rule) = self.0.get(host) {
+                return Some(rule);
+            }
+
+            host = host.split_once('.')
+                .map(|(_, parent)| parent)
+                .filter(|parent| parent.contains('.'))?;
+        }
+    }
+}
+
+fn normalize_host(host: &str) -> &str {
+    let host = host.trim_start_matches('.');
+    host.strip_prefix("www.").unwrap_or(host)
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        const RULE: &str = "
+            # Example.
+            body: div.article-body
+            body: article
+            title: h1.headline
+            strip_id_or_class: promo
+            prefer_generic: yes
+            single_page_link: a.print
+        ";
+
+        let rule: SiteRule = RULE.parse().unwrap();
+        assert_eq!(rule.body, ["div.article-body", "article"]);
+        assert_eq!(rule.title, ["h1.headline"]);
+        assert_eq!(rule.strip, ["[id*=\"promo\"], [class*=\"promo\"]"]);
+        assert!(rule.prefer_generic);
+
+        match "body: ][".parse::<SiteRule>() {
+            Err(SiteRuleError::Syntax { line: 1, .. }) => {},
+            _ => panic!("expected a syntax error"),
+        }
+    }
+
+    #[test]
+    fn test_find() {
+        let mut rules = SiteRules::new();
+        rules.add("www.example.com", SiteRule::default());
+
+        assert!(rules.find("example.com").is_some());
+        assert!(rules.find("WWW.Example.com").is_some());
+        assert!(rules.find("news.example.com").is_some());
+        assert!(rules.find("example.org").is_none());
+        assert!(rules.find("com").is_none());
+    }
+}
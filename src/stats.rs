@@ -0,0 +1,147 @@
+use std::cmp;
+use std::time::Duration;
+
+use kuchiki::NodeRef;
+use kuchiki::iter::NodeEdge;
+
+
+// Every image adds some time: 12 seconds for the first one, then a second less for every next one,
+// but at least 3 seconds, like Medium does.
+const FIRST_IMAGE_SECS: u32 = 12;
+const MIN_IMAGE_SECS: u32 = 3;
+
+
+/// Returns the text of the node with block elements separated by spaces.
+pub fn text_of(root: &NodeRef) -> String {
+    let mut text = String::new();
+
+    for edge in root.traverse() {
+        match edge {
+            NodeEdge::Start(node) if node.as_text().is_some() => {
+                text.push_str(&node.as_text().unwrap().borrow());
+            },
+            NodeEdge::Start(node) | NodeEdge::End(node) => {
+                if node.as_element().is_some_and(|elem| !is_inline(&elem.name.local)) {
+                    text.push(' ');
+                }
+            }
+        }
+    }
+
+    text
+}
+
+fn is_inline(name: &str) -> bool {
+    matches!(name,
+        "a" | "abbr" | "b" | "bdi" | "bdo" | "cite" | "code" | "data" | "del" | "dfn" | "em" |
+        "font" | "i" | "ins" | "kbd" | "mark" | "q" | "s" | "samp" | "small" | "span" |
+        "strong" | "sub" | "sup" | "time" | "u" | "var"
+    )
+}
+
+/// Counts words, where every CJK ideograph or kana is counted as a separate word.
+///
+/// Returns the total number of words and the number of CJK ones.
+pub fn count_words(text: &str) -> (u32, u32) {
+    let mut word_cnt = 0;
+    let mut cjk_cnt = 0;
+
+    for word in text.split_whitespace() {
+        let cjk = word.chars().filter(|&ch| is_cjk(ch)).count() as u32;
+
+        // Ignore standalone punctuation like dashes.
+        if word.chars().any(|ch| ch.is_alphanumeric() && !is_cjk(ch)) {
+            word_cnt += 1;
+        }
+
+        cjk_cnt += cjk;
+    }
+
+    (word_cnt + cjk_cnt, cjk_cnt)
+}
+
+/// Estimates the time to read the text and look at the images.
+pub fn reading_time(word_cnt: u32, cjk_cnt: u32, img_cnt: u32, wpm: u32, cjk_cpm: u32) -> Duration {
+    let minutes = (word_cnt - cjk_cnt) as f32 / wpm.max(1) as f32
+        + cjk_cnt as f32 / cjk_cpm.max(1) as f32;
+
+    let img_secs = (0..img_cnt)
+        .map(|index| cmp::max(FIRST_IMAGE_SECS.saturating_sub(index), MIN_IMAGE_SECS))
+        .sum::<u32>();
+
+    Duration::from_secs_f32(minutes * 60.) + Duration::from_secs(img_secs.into())
+}
+
+/// Detects the direction of the text by the prevailing script.
+pub fn detect_direction(text: &str) -> Option<&'static str> {
+    let (mut rtl_cnt, mut ltr_cnt) = (0, 0);
+
+    for ch in text.chars().filter(|ch| ch.is_alphabetic()) {
+        if is_rtl(ch) {
+            rtl_cnt += 1;
+        } else {
+            ltr_cnt += 1;
+        }
+    }
+
+    match (rtl_cnt, ltr_cnt) {
+        (0, 0) => None,
+        _ if rtl_cnt > ltr_cnt => Some("rtl"),
+        _ => Some("ltr")
+    }
+}
+
+fn is_rtl(ch: char) -> bool {
+    matches!(ch,
+        '\u{0590}'..='\u{08ff}' |   // Hebrew, Arabic, Syriac, Thaana, NKo and others
+        '\u{fb1d}'..='\u{fdff}' |   // Hebrew and Arabic presentation forms
+        '\u{fe70}'..='\u{feff}'     // Arabic presentation forms
+    )
+}
+
+/// Checks if the char belongs to a script that doesn't separate words with spaces.
+pub fn is_unspaced(ch: char) -> bool {
+    is_cjk(ch) || matches!(ch,
+        '\u{0e00}'..='\u{0eff}' |   // Thai and Lao
+        '\u{1000}'..='\u{109f}' |   // Myanmar
+        '\u{1780}'..='\u{17ff}'     // Khmer
+    )
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch,
+        '\u{3040}'..='\u{30ff}' |   // Hiragana and Katakana
+        '\u{3400}'..='\u{4dbf}' |   // CJK Unified Ideographs Extension A
+        '\u{4e00}'..='\u{9fff}' |   // CJK Unified Ideographs
+        '\u{f900}'..='\u{faff}' |   // CJK Compatibility Ideographs
+        '\u{20000}'..='\u{2fa1f}'   // CJK Unified Ideographs Extension B and later
+    )
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+
+    #[test]
+    fn test_count_words() {
+        assert_eq!(count_words("  Hello, world! It's 2020. "), (4, 0));
+        assert_eq!(count_words("Rust 是一种编程语言。"), (8, 7));
+        assert_eq!(count_words("— …"), (0, 0));
+    }
+
+    #[test]
+    fn test_detect_direction() {
+        assert_eq!(detect_direction("Hello, world!"), Some("ltr"));
+        assert_eq!(detect_direction("مرحبا بالعالم, hello"), Some("rtl"));
+        assert_eq!(detect_direction("שלום עולם"), Some("rtl"));
+        assert_eq!(detect_direction("123 !?"), None);
+    }
+
+    #[test]
+    fn test_reading_time() {
+        assert_eq!(reading_time(400, 0, 0, 200, 500), Duration::from_secs(120));
+        assert_eq!(reading_time(500, 500, 0, 200, 500), Duration::from_secs(60));
+        assert_eq!(reading_time(0, 0, 12, 200, 500), Duration::from_secs(12 + 11 + 10 + 9 + 8 + 7 + 6 + 5 + 4 + 3 + 3 + 3));
+    }
+}
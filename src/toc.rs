@@ -0,0 +1,254 @@
+use std::collections::HashSet;
+
+use html5ever::{LocalName, local_name};
+use kuchiki::{ElementData, NodeDataRef, NodeRef};
+use kuchiki::iter::NodeIterator;
+use lazy_static::lazy_static;
+use regex::Regex;
+
+
+lazy_static! {
+    // Links to edit the section, e.g. `span.mw-editsection` of MediaWiki.
+    static ref EDIT_SECTION: Regex = Regex::new(r"(?xi)
+        \b(?:mw-editsection|editsection|edit-section|section-edit)\b
+    ").unwrap();
+}
+
+
+/// An entry of the table of contents built from `<h1>`-`<h6>` of the content.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TocEntry {
+    /// The level of the heading, from 1 to 6.
+    pub level: u8,
+    pub text: String,
+    /// The existing `id` of the heading or of its only anchor, otherwise a slug of the text,
+    /// unique within the content.
+    pub id: String,
+    /// Entries of lower level headings following this one.
+    pub children: Vec<TocEntry>,
+}
+
+
+/// Collects headings into a tree and optionally sets their ids.
+pub fn build(content: &NodeRef, inject_ids: bool) -> Vec<TocEntry> {
+    // Ids of other elements are reserved, e.g. anchors of footnotes.
+    let mut used = content.inclusive_descendants().elements()
+        .filter(|elem| heading_level(&elem.name.local).is_none())
+        .filter_map(|elem| elem.attributes.borrow().get(local_name!("id")).map(String::from))
+        .collect::<HashSet<_>>();
+
+    // Anchors inside headings are reserved too, but can be reused by their own headings.
+    let outside = content.inclusive_descendants().elements()
+        .filter(|elem| !elem.as_node().inclusive_ancestors().elements().any(|elem| is_heading(&elem)))
+        .filter_map(|elem| elem.attributes.borrow().get(local_name!("id")).map(String::from))
+        .collect::<HashSet<_>>();
+
+    let mut assigned = HashSet::new();
+
+    let mut flat = Vec::new();
+
+    for elem in content.inclusive_descendants().elements() {
+        let level = match heading_level(&elem.name.local) {
+            Some(level) => level,
+            None => continue
+        };
+
+        let mut text = String::new();
+        text_without_edit_links(elem.as_node(), &mut text);
+        let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+
+        if text.is_empty() {
+            continue;
+        }
+
+        let mut attributes = elem.attributes.borrow_mut();
+
+        let (id, is_own) = match attributes.get(local_name!("id")) {
+            Some(id) if !id.is_empty() && !used.contains(id) => (id.to_string(), true),
+            Some(_) => (deduplicate(slugify(&text), &used), true),
+            None => match anchor_id(&elem) {
+                Some(id) if !id.is_empty() && !outside.contains(&id) && !assigned.contains(&id) => (id, false),
+                _ => (deduplicate(slugify(&text), &used), true)
+            }
+        };
+
+        // An anchor inside is already a target, the same id must not be set twice.
+        if inject_ids && is_own && attributes.get(local_name!("id")) != Some(&id) {
+            attributes.insert(local_name!("id"), id.clone());
+        }
+
+        used.insert(id.clone());
+        assigned.insert(id.clone());
+        flat.push(TocEntry { level, text, id, children: Vec::new() });
+    }
+
+    nest(flat)
+}
+
+fn is_heading(elem: &ElementData) -> bool {
+    heading_level(&elem.name.local).is_some()
+}
+
+// Returns the id of the only `<a>` or `<span>` with an id, e.g. `span.mw-headline` of MediaWiki.
+fn anchor_id(heading: &NodeDataRef<ElementData>) -> Option<String> {
+    let mut ids = heading.as_node().descendants().elements()
+        .filter(|elem| matches!(elem.name.local, local_name!("a") | local_name!("span")))
+        .filter(|elem| !is_edit_link(elem))
+        .filter_map(|elem| elem.attributes.borrow().get(local_name!("id")).map(String::from));
+
+    match (ids.next(), ids.next()) {
+        (Some(id), None) => Some(id),
+        _ => None
+    }
+}
+
+fn text_without_edit_links(node: &NodeRef, text: &mut String) {
+    for child in node.children() {
+        if let Some(data) = child.as_text() {
+            text.push_str(&data.borrow());
+        } else if let Some(elem) = child.as_element() {
+            if !is_edit_link(elem) {
+                text_without_edit_links(&child, text);
+            }
+        }
+    }
+}
+
+fn is_edit_link(elem: &ElementData) -> bool {
+    elem.attributes.borrow().get(local_name!("class")).is_some_and(|class| EDIT_SECTION.is_match(class))
+}
+
+fn heading_level(name: &LocalName) -> Option<u8> {
+    match *name {
+        local_name!("h1") => Some(1),
+        local_name!("h2") => Some(2),
+        local_name!("h3") => Some(3),
+        local_name!("h4") => Some(4),
+        local_name!("h5") => Some(5),
+        local_name!("h6") => Some(6),
+        _ => None
+    }
+}
+
+// Every heading becomes a child of the closest previous heading of a higher level.
+fn nest(flat: Vec<TocEntry>) -> Vec<TocEntry> {
+    let mut roots = Vec::new();
+    let mut stack: Vec<TocEntry> = Vec::new();
+
+    for entry in flat {
+        while stack.last().is_some_and(|last| last.level >= entry.level) {
+            pop_into(&mut stack, &mut roots);
+        }
+
+        stack.push(entry);
+    }
+
+    while !stack.is_empty() {
+        pop_into(&mut stack, &mut roots);
+    }
+
+    roots
+}
+
+fn pop_into(stack: &mut Vec<TocEntry>, roots: &mut Vec<TocEntry>) {
+    let entry = stack.pop().unwrap();
+
+    match stack.last_mut() {
+        Some(parent) => parent.children.push(entry),
+        None => roots.push(entry)
+    }
+}
+
+// Lowercases letters and digits, everything else is replaced with single hyphens.
+fn slugify(text: &str) -> String {
+    let mut slug = String::new();
+
+    for ch in text.chars() {
+        if ch.is_alphanumeric() {
+            slug.extend(ch.to_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+
+    let slug = slug.trim_end_matches('-');
+
+    if slug.is_empty() { "section".to_string() } else { slug.to_string() }
+}
+
+fn deduplicate(slug: String, used: &HashSet<String>) -> String {
+    if !used.contains(&slug) {
+        return slug;
+    }
+
+    (1..).map(|index| format!("{}-{}", slug, index))
+        .find(|candidate| !used.contains(candidate))
+        .unwrap()
+}
+
+
+mod tests {
+    #![cfg(test)]
+    use super::*;
+    use kuchiki::traits::TendrilSink;
+
+    #[test]
+    fn test_slugify() {
+        assert_eq!(slugify("Hello, World!"), "hello-world");
+        assert_eq!(slugify("  1.2 Über  uns "), "1-2-über-uns");
+        assert_eq!(slugify("?!"), "section");
+    }
+
+    #[test]
+    fn test_build() {
+        let root = kuchiki::parse_html().one("<div>
+            <h2>Intro</h2>
+            <h3>Details</h3><h4>Deep</h4>
+            <h3 id=\"custom\">More</h3>
+            <h3>More</h3>
+            <h2>Intro</h2>
+            <h4>Skipped level</h4>
+            <h2> </h2>
+            <p id=\"more\">Text</p>
+        </div>");
+
+        let toc = build(&root, true);
+
+        fn flatten(entries: &[TocEntry]) -> Vec<(u8, &str, usize)> {
+            entries.iter().map(|entry| (entry.level, &entry.id[..], entry.children.len())).collect()
+        }
+
+        assert_eq!(flatten(&toc), [(2, "intro", 3), (2, "intro-1", 1)]);
+        assert_eq!(flatten(&toc[0].children), [(3, "details", 1), (3, "custom", 0), (3, "more-1", 0)]);
+        assert_eq!(flatten(&toc[0].children[0].children), [(4, "deep", 0)]);
+        assert_eq!(flatten(&toc[1].children), [(4, "skipped-level", 0)]);
+
+        let ids = root.select("h2, h3, h4").unwrap()
+            .filter_map(|h| h.attributes.borrow().get("id").map(String::from))
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, ["intro", "details", "deep", "custom", "more-1", "intro-1", "skipped-level"]);
+
+        // Ids are stable if the tree is processed again.
+        assert_eq!(build(&root, true), toc);
+    }
+
+    #[test]
+    fn test_build_mediawiki() {
+        let root = kuchiki::parse_html().one("<div>
+            <h2><span class=\"mw-headline\" id=\"History\">History</span><span class=\"mw-editsection\">\
+                <span class=\"mw-editsection-bracket\">[</span><a href=\"/edit\">edit</a>]</span></h2>
+            <h2><a id=\"History\"></a>History</h2>
+        </div>");
+
+        let toc = build(&root, true);
+        let entries = toc.iter().map(|entry| (&entry.text[..], &entry.id[..])).collect::<Vec<_>>();
+        assert_eq!(entries, [("History", "History"), ("History", "history")]);
+
+        let ids = root.select("h2").unwrap()
+            .map(|h| h.attributes.borrow().get("id").map(String::from))
+            .collect::<Vec<_>>();
+
+        assert_eq!(ids, [None, Some("history".to_string())]);
+    }
+}
@@ -1,12 +1,17 @@
 use std::io::Write;
 
-use kuchiki::NodeRef;
+use kuchiki::{ElementData, NodeDataRef, NodeRef};
 use kuchiki::NodeData::*;
+use kuchiki::iter::NodeIterator;
 use kuchiki::traits::TendrilSink;
+use regex::Regex;
 use serde::Deserialize;
 use url::Url;
 
-use readable_readability::{Readability, Metadata};
+use readable_readability::{
+    AttributeAllowlist, BubbleAction, CaptureAction, ExtractionHook, Heuristic, LinkNormalizer,
+    Metadata, NodeInfo, Readability, ScoringParams, SiteRule, SiteRules,
+};
 
 
 // duplicate the Metadata struct so we can implement Deserialize
@@ -49,7 +54,7 @@ fn compare_trees(actual: &NodeRef, expected: &NodeRef) {
 }
 
 fn is_not_empty_text(node: &NodeRef) -> bool {
-    !node.as_text().map_or(false, |text| text.borrow().trim().is_empty())
+    !node.as_text().is_some_and(|text| text.borrow().trim().is_empty())
 }
 
 fn compare_nodes(actual: &NodeRef, expected: &NodeRef) {
@@ -57,16 +62,16 @@ fn compare_nodes(actual: &NodeRef, expected: &NodeRef) {
     let expected_data = expected.data();
 
     match (actual_data, expected_data) {
-        (&Element(ref actual_data), &Element(ref expected_data)) => {
+        (Element(actual_data), Element(expected_data)) => {
             let actual_attributes = &actual_data.attributes.borrow().map;
             let expected_attributes = &expected_data.attributes.borrow().map;
 
             if actual_data.name != expected_data.name || actual_attributes != expected_attributes {
-                panic!("{} != {}", stringify_node(&actual), stringify_node(&expected));
+                panic!("{} != {}", stringify_node(actual), stringify_node(expected));
             }
         },
 
-        (&Text(ref actual), &Text(ref expected)) => {
+        (Text(actual), Text(expected)) => {
             let actual = actual.borrow();
             let expected = expected.borrow();
 
@@ -149,6 +154,9 @@ macro_rules! test_sample {
 test_sample!(base_url);
 test_sample!(social_buttons);
 test_sample!(replace_font_tags);
+test_sample!(figures);
+test_sample!(tables);
+test_sample!(embeds);
 
 test_sample!(bbc);
 test_sample!(buzzfeed);
@@ -166,3 +174,891 @@ test_sample!(nytimes_1);
 test_sample!(wikia);
 test_sample!(wikipedia);
 test_sample!(wordpress);
+
+#[test]
+fn figure_classes_without_images() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt.</p>
+        <div class=\"photo-essay\">
+            <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea.</p>
+            <div class=\"share\"><a href=\"/twitter\">Tweet</a> <a href=\"/facebook\">Share</a></div>
+        </div>
+        <div class=\"photo\">
+            <img src=\"photo.jpg\">
+            <div class=\"share\">Photo by Jane Doe</div>
+        </div>
+        <p>Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla.</p>
+    </article>";
+
+    // Only figures with images protect their content.
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    let text = actual_tree.text_contents();
+    assert!(!text.contains("Tweet"), "{}", actual_tree.to_string());
+    assert!(text.contains("Ut enim"), "{}", actual_tree.to_string());
+    assert!(text.contains("Photo by Jane Doe"), "{}", actual_tree.to_string());
+}
+
+#[test]
+fn custom_allowed_embeds() {
+    static SOURCE: &str = include_sample_file!(embeds, "source.html");
+
+    let (actual_tree, _) = Readability::new()
+        .allowed_embeds(Regex::new(r"//ads\.example\.com").unwrap())
+        .parse(SOURCE);
+
+    let iframes = actual_tree.select("iframe").unwrap()
+        .map(|iframe| iframe.attributes.borrow().get("src").unwrap().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(iframes, ["https://ads.example.com/banner"]);
+}
+
+#[test]
+fn self_hosted_media() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt.</p>
+        <div><p>Watch the clip</p><video src=\"/clip.mp4\" controls></video></div>
+        <div><p>Listen to it</p><audio controls><source src=\"http://example.com/talk.mp3\"></audio></div>
+        <div><p>Advertisement</p><audio autoplay src=\"https://ads.example.net/jingle.mp3\"></audio></div>
+        <p>Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla.</p>
+    </article>";
+
+    let (actual_tree, _) = Readability::new()
+        .base_url(Url::parse("http://example.com/article").unwrap())
+        .parse(SOURCE);
+
+    let text = actual_tree.text_contents();
+    assert!(text.contains("Watch the clip") && text.contains("Listen to it"), "{}", actual_tree.to_string());
+    assert!(!text.contains("Advertisement"), "{}", actual_tree.to_string());
+    assert_eq!(actual_tree.select("video, audio").unwrap().count(), 2);
+
+    // Frames of the site itself must match the pattern anyway.
+    let source = SOURCE.replace("<p>Duis", "<div><p>Sponsored</p><iframe src=\"/ads/frame.html\"></iframe></div>\n<p>Duis");
+    let (actual_tree, _) = Readability::new()
+        .base_url(Url::parse("http://example.com/article").unwrap())
+        .allowed_embeds(Regex::new(r"[^\s\S]").unwrap())
+        .parse(&source);
+
+    assert_eq!(actual_tree.select("iframe").unwrap().count(), 0, "{}", actual_tree.to_string());
+    assert_eq!(actual_tree.select("video, audio").unwrap().count(), 2);
+}
+
+#[test]
+fn strip_and_keep_tags() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <aside><p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris.</p></aside>
+        <h2>Duis aute irure dolor</h2>
+        <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+    </article>";
+
+    let count = |tree: &NodeRef, selector| tree.select(selector).unwrap().count();
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    assert_eq!(count(&actual_tree, "aside"), 0);
+    assert_eq!(count(&actual_tree, "h2"), 1);
+
+    let (actual_tree, _) = Readability::new()
+        .keep_tag("ASIDE")
+        .strip_tag("H2")
+        .parse(SOURCE);
+    assert_eq!(count(&actual_tree, "aside"), 1);
+    assert_eq!(count(&actual_tree, "h2"), 0);
+}
+
+#[test]
+fn extend_heuristic() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <div class=\"paywall-teaser\">
+            <p>Subscribe to read the rest of the article.</p>
+            <p>Already a subscriber? Log in to continue.</p>
+        </div>
+        <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    assert!(actual_tree.select_first(".paywall-teaser").is_ok());
+
+    let (actual_tree, _) = Readability::new()
+        .extend_heuristic(Heuristic::Negative, ["paywall-teaser"])
+        .parse(SOURCE);
+    assert!(actual_tree.select_first(".paywall-teaser").is_err());
+}
+
+#[test]
+fn custom_scoring() {
+    static SOURCE: &str = include_sample_file!(replace_font_tags, "source.html");
+
+    let tag_name = |tree: &NodeRef| tree.as_element().unwrap().name.local.to_string();
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    assert_eq!(tag_name(&actual_tree), "article");
+
+    // No paragraph is long enough to be scored, so there are no candidates at all.
+    let (actual_tree, _) = Readability::new()
+        .scoring(ScoringParams { min_paragraph_len: 1000, ..Default::default() })
+        .parse(SOURCE);
+    assert_eq!(tag_name(&actual_tree), "body");
+
+    // Zero is treated as one rather than dividing by it.
+    let (actual_tree, _) = Readability::new()
+        .scoring(ScoringParams { chars_per_point: 0, ..Default::default() })
+        .parse(SOURCE);
+    assert_eq!(tag_name(&actual_tree), "article");
+}
+
+#[test]
+fn site_rules() {
+    static SOURCE: &str = "<html><head><title>Page</title></head><body>
+        <h1 class=\"headline\">Site rule title</h1>
+        <span class=\"by\">Jane Doe</span>
+        <time datetime=\"2020-01-02\">January 2</time>
+        <div class=\"story\">
+            <p>Short, but it's the story.</p>
+            <div class=\"newsletter\">Sign up!</div>
+        </div>
+        <div class=\"other\">
+            <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+            <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+        </div>
+    </body></html>";
+
+    let rule: SiteRule = "
+        body: .story
+        title: .headline
+        author: .by
+        date: time
+        strip: .newsletter
+    ".parse().unwrap();
+
+    let mut rules = SiteRules::new();
+    rules.add("example.com", rule.clone());
+
+    let (actual_tree, actual_meta) = Readability::new()
+        .base_url(Url::parse("http://www.example.com/article").unwrap())
+        .site_rules(rules)
+        .parse(SOURCE);
+
+    assert_eq!(actual_tree.text_contents().trim(), "Short, but it's the story.");
+    assert_eq!(actual_meta.article_title.as_deref(), Some("Site rule title"));
+    assert_eq!(actual_meta.byline.as_deref(), Some("Jane Doe"));
+    assert_eq!(actual_meta.published_time.as_deref(), Some("2020-01-02"));
+
+    // The generic extraction succeeds, so the body selector isn't used.
+    let mut rules = SiteRules::new();
+    rules.add("example.com", SiteRule { prefer_generic: true, ..rule });
+
+    let (actual_tree, _) = Readability::new()
+        .base_url(Url::parse("http://example.com/article").unwrap())
+        .site_rules(rules)
+        .parse(SOURCE);
+
+    assert!(actual_tree.text_contents().contains("Lorem ipsum"));
+    assert!(!actual_tree.text_contents().contains("Sign up!"));
+}
+
+#[test]
+fn site_rules_strip_body() {
+    static SOURCE: &str = "<body><div class=\"post\">
+        <p>Short, but it's the story.</p>
+        <script>alert(1)</script><noscript>x</noscript><!-- c --><form><input></form>
+        <div class=\"promo\">Buy now!</div>
+    </div></body>";
+
+    let rule: SiteRule = "body: div.post".parse().unwrap();
+
+    for prefer_generic in [false, true] {
+        let mut rules = SiteRules::new();
+        rules.add("example.com", SiteRule { prefer_generic, ..rule.clone() });
+
+        let (actual_tree, _) = Readability::new()
+            .base_url(Url::parse("http://example.com/article").unwrap())
+            .site_rules(rules)
+            .remove_selector(".promo")
+            .parse(SOURCE);
+
+        let html = actual_tree.to_string();
+        assert!(actual_tree.select_first("script, noscript, input").is_err(), "{}", html);
+        assert!(!html.contains("<!--") && !html.contains("Buy now!"), "{}", html);
+        assert!(html.contains("the story"), "{}", html);
+    }
+}
+
+#[test]
+fn remove_and_keep_selectors() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <div class=\"signup\">
+            <p>Subscribe to our newsletter to get the best stories of the week.</p>
+            <p>We never share your email with anyone.</p>
+        </div>
+        <div class=\"related\"><a href=\"/one\">One</a> <a href=\"/two\">Two</a></div>
+        <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    assert!(actual_tree.select_first(".signup").is_ok());
+    assert!(actual_tree.select_first(".related").is_err());
+
+    let (actual_tree, _) = Readability::new()
+        .remove_selector(".signup")
+        .keep_selector("div.related")
+        .parse(SOURCE);
+    assert!(actual_tree.select_first(".signup").is_err());
+    assert_eq!(actual_tree.select("div.related a").unwrap().count(), 2);
+
+    // Kept elements stay kept when they are renamed, e.g. cells of layout tables.
+    static LAYOUT: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <table><tr><td class=\"links\"><div><a href=\"/one\">One</a></div><div><a href=\"/two\">Two</a></div></td></tr></table>
+        <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(LAYOUT);
+    assert!(actual_tree.select_first(".links").is_err());
+
+    let (actual_tree, _) = Readability::new().keep_selector("td.links").parse(LAYOUT);
+    assert_eq!(actual_tree.select(".links a").unwrap().count(), 2, "{}", actual_tree.to_string());
+}
+
+#[test]
+fn extraction_hooks() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <div class=\"paywall\"><p>Subscribe to continue reading this excellent article.</p></div>
+        <x-para>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi.</x-para>
+        <div class=\"see-also\"><a href=\"/one\">One</a> <a href=\"/two\">Two</a></div>
+        <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+    </article>";
+
+    struct Hook {
+        scored: u32,
+    }
+
+    impl ExtractionHook for Hook {
+        fn before_parse(&mut self, html: &str) -> Option<String> {
+            Some(html.replace("Lorem ipsum", "LOREM IPSUM"))
+        }
+
+        fn on_capture(&mut self, elem: &NodeDataRef<ElementData>) -> CaptureAction {
+            match &*elem.name.local {
+                "x-para" => CaptureAction::Rename("p".into()),
+                _ if elem.attributes.borrow().get("class") == Some("paywall") => CaptureAction::Remove,
+                _ => CaptureAction::Continue,
+            }
+        }
+
+        fn on_bubble(&mut self, elem: &NodeDataRef<ElementData>, _info: &NodeInfo) -> BubbleAction {
+            match elem.attributes.borrow().get("class") {
+                Some("see-also") => BubbleAction::Keep,
+                _ => BubbleAction::Continue,
+            }
+        }
+
+        fn adjust_score(&mut self, _: &NodeDataRef<ElementData>, _: &NodeInfo, score: f32) -> f32 {
+            self.scored += 1;
+            score
+        }
+
+        fn post_process(&mut self, _content: &NodeRef, metadata: &mut Metadata) {
+            assert!(self.scored > 0);
+            metadata.byline = Some("Hooked".into());
+        }
+    }
+
+    let (actual_tree, actual_meta) = Readability::new()
+        .hook(Hook { scored: 0 })
+        .parse(SOURCE);
+
+    let text = actual_tree.text_contents();
+    assert!(text.contains("LOREM IPSUM"));
+    assert!(!text.contains("Subscribe"));
+    assert!(actual_tree.select_first("x-para").is_err());
+    assert_eq!(actual_tree.select("p").unwrap().count(), 3);
+    assert_eq!(actual_tree.select("div.see-also a").unwrap().count(), 2);
+    assert_eq!(actual_meta.byline.as_deref(), Some("Hooked"));
+}
+
+#[test]
+fn hook_removes_best_candidate() {
+    static SOURCE: &str = "<body>
+        <div class=\"post\">
+            <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+            <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip.</p>
+            <p>Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu.</p>
+        </div>
+        <div class=\"other\">
+            <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+        </div>
+    </body>";
+
+    struct Hook;
+
+    impl ExtractionHook for Hook {
+        fn on_bubble(&mut self, elem: &NodeDataRef<ElementData>, _info: &NodeInfo) -> BubbleAction {
+            match elem.attributes.borrow().get("class") {
+                Some("post") => BubbleAction::Remove,
+                _ => BubbleAction::Continue,
+            }
+        }
+    }
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    assert!(actual_tree.text_contents().contains("Lorem ipsum"));
+
+    let (actual_tree, _) = Readability::new().hook(Hook).parse(SOURCE);
+    let text = actual_tree.text_contents();
+    assert!(!text.contains("Lorem ipsum"), "{}", actual_tree.to_string());
+    assert!(text.contains("Excepteur"));
+}
+
+#[test]
+fn multiple_pages() {
+    static PAGE_1: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip.</p>
+    </article>
+    <div class=\"pagination\"><a href=\"?page=2\">2</a> <a href=\"?page=3\">3</a> <a href=\"?page=2\">Next »</a></div>";
+
+    static PAGE_2: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <p>Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu.</p>
+        <p>See <a href=\"notes\">the notes</a> for the rest of sit amet, consectetur adipisicing.</p>
+    </article>
+    <div class=\"pagination\"><a href=\"?page=1\">1</a> <a href=\"?page=3\">3</a> <a href=\"?page=3\">Next »</a></div>";
+
+    static PAGE_3: &str = "<article>
+        <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia deserunt.</p>
+        <p>Quis autem vel eum iure reprehenderit qui in ea voluptate velit esse quam nihil.</p>
+    </article>
+    <div class=\"pagination\"><a href=\"?page=1\">1</a> <a href=\"?page=2\">2</a></div>";
+
+    let url = |page: u32| Url::parse(&format!("http://example.com/pages/story?page={}", page)).unwrap();
+
+    let (_, meta) = Readability::new().base_url(url(1)).parse(PAGE_1);
+    assert_eq!(meta.next_page_url.as_deref(), Some("http://example.com/pages/story?page=2"));
+
+    struct Hook {
+        calls: u32,
+    }
+
+    impl ExtractionHook for Hook {
+        fn post_process(&mut self, _content: &NodeRef, _metadata: &mut Metadata) {
+            self.calls += 1;
+            assert_eq!(self.calls, 1);
+        }
+    }
+
+    let (actual_tree, actual_meta) = Readability::new()
+        .hook(Hook { calls: 0 })
+        .parse_pages([(url(1), PAGE_1), (url(2), PAGE_2), (url(3), PAGE_3)]);
+
+    let text = actual_tree.text_contents();
+    assert_eq!(text.matches("Lorem ipsum").count(), 1);
+    assert!(text.contains("Ut enim"));
+    assert!(text.contains("Duis aute"));
+    assert!(text.contains("Excepteur"));
+    assert_eq!(actual_tree.select("p").unwrap().count(), 6);
+    assert_eq!(actual_meta.next_page_url, None);
+
+    let link = actual_tree.select_first("a").unwrap();
+    assert_eq!(link.attributes.borrow().get("href"), Some("http://example.com/pages/notes"));
+}
+
+#[test]
+fn remove_title_heading() {
+    static SOURCE: &str = "<head>
+        <meta property=\"og:title\" content=\"The Quick Brown Fox Jumps Over the Lazy Dog\">
+    </head>
+    <body><article>
+        <h2>The quick brown fox jumps over the lazy dog!</h2>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>
+        <h3>The lazy dog</h3>
+        <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+    </article></body>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    assert!(actual_tree.select_first("h2").is_ok());
+
+    let (actual_tree, _) = Readability::new().remove_title_heading(true).parse(SOURCE);
+    assert!(actual_tree.select_first("h2").is_err());
+    assert!(actual_tree.select_first("h3").is_ok());
+
+    let other = SOURCE.replace(" over the lazy dog!</h2>", "</h2>");
+    let (actual_tree, _) = Readability::new().remove_title_heading(true).parse(&other);
+    assert!(actual_tree.select_first("h2").is_err());
+
+    let other = SOURCE.replace("The quick brown fox jumps over the lazy dog!", "Introduction");
+    let (actual_tree, _) = Readability::new().remove_title_heading(true).parse(&other);
+    assert!(actual_tree.select_first("h2").is_ok());
+}
+
+#[test]
+fn generated_excerpt() {
+    static SOURCE: &str = "<body>
+        <p class=\"cookies\">We use cookies.</p>
+        <article>
+            <p>Short.</p>
+            <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit. Sed do eiusmod tempor.</p>
+            <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa qui officia.</p>
+        </article>
+    </body>";
+
+    let (_, actual_meta) = Readability::new().parse(SOURCE);
+    assert_eq!(actual_meta.description.as_deref(),
+               Some("Lorem ipsum dolor sit amet, consectetur adipisicing elit. Sed do eiusmod tempor."));
+    assert!(actual_meta.is_description_generated);
+
+    let (_, actual_meta) = Readability::new().excerpt_length(40).parse(SOURCE);
+    assert_eq!(actual_meta.description.as_deref(), Some("Lorem ipsum dolor sit amet, consectetur…"));
+
+    let source = SOURCE.replace("<body>", "<meta name=\"description\" content=\"Meta\"><body>");
+    let (_, actual_meta) = Readability::new().parse(&source);
+    assert_eq!(actual_meta.description.as_deref(), Some("Meta"));
+    assert!(!actual_meta.is_description_generated);
+}
+
+#[test]
+fn reading_time() {
+    let paragraph = "<p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>";
+    let source = format!("<article>{}<img src=\"a.png\"></article>", paragraph.repeat(30));
+
+    let (_, actual_meta) = Readability::new().words_per_minute(120).parse(&source);
+    assert_eq!(actual_meta.word_count, 12 * 30);
+    assert_eq!(actual_meta.char_count, 80 * 30 + 29);
+    assert_eq!(actual_meta.reading_time.as_secs(), 3 * 60 + 12);
+}
+
+#[test]
+fn unspaced_scripts() {
+    let paragraph = "<p>这是一个段落，包含汉字、标点。</p>";
+    let source = format!("<body>
+        <div id=\"menu\"><p>首页</p><p>新闻</p></div>
+        <div id=\"story\">{}</div>
+        <div id=\"footer-links\"><p>关于我们</p></div>
+    </body>", paragraph.repeat(5));
+
+    let (actual_tree, actual_meta) = Readability::new().parse(&source);
+    assert_eq!(actual_tree.as_element().unwrap().attributes.borrow().get("id"), Some("story"));
+    assert_eq!(actual_meta.char_count, 15 * 5 + 4);
+
+    let params = ScoringParams { unspaced_char_weight: 1, ..ScoringParams::default() };
+    let (actual_tree, _) = Readability::new().scoring(params).parse(&source);
+    assert_ne!(actual_tree.as_element().unwrap().attributes.borrow().get("id"), Some("story"));
+}
+
+#[test]
+fn language() {
+    static SOURCE: &str = "<html><head>
+        <meta http-equiv=\"Content-Language\" content=\"en_GB, fr\">
+    </head><body><article>
+        <p>Der schnelle braune Fuchs springt über den faulen Hund, während die Katze schläft.</p>
+        <p>Die Kinder spielen im Garten und die Eltern trinken Kaffee auf der Terrasse.</p>
+    </article></body></html>";
+
+    let (_, actual_meta) = Readability::new().parse(SOURCE);
+    assert_eq!(actual_meta.declared_lang.as_deref(), Some("en-GB"));
+
+    if cfg!(feature = "language-detection") {
+        assert_eq!(actual_meta.detected_lang.map(|lang| lang.tag).as_deref(), Some("de"));
+        assert_eq!(actual_meta.lang.as_deref(), Some("de"));
+    } else {
+        assert_eq!(actual_meta.detected_lang, None);
+        assert_eq!(actual_meta.lang.as_deref(), Some("en-GB"));
+    }
+
+    let source = SOURCE.replace("<html>", "<html lang=\"de-AT\">");
+    let (_, actual_meta) = Readability::new().parse(&source);
+    assert_eq!(actual_meta.lang.as_deref(), Some("de-AT"));
+}
+
+#[test]
+fn text_direction() {
+    let paragraph = "<p>هذا نص عربي طويل بما يكفي ليكون فقرة في المقالة، ويحتوي على بعض الفواصل، والكلمات.</p>";
+    let source = format!("<html><body><div class=\"article\">{}</div></body></html>", paragraph.repeat(3));
+
+    let (actual_tree, actual_meta) = Readability::new().parse(&source);
+    assert_eq!(actual_meta.dir.as_deref(), Some("rtl"));
+    assert_eq!(actual_tree.as_element().unwrap().attributes.borrow().get("dir"), Some("rtl"));
+
+    let source = source.replace("<html>", "<html dir=\"ltr\">");
+    let (actual_tree, actual_meta) = Readability::new().parse(&source);
+    assert_eq!(actual_meta.dir.as_deref(), Some("ltr"));
+    assert_eq!(actual_tree.as_element().unwrap().attributes.borrow().get("dir"), Some("ltr"));
+
+    let paragraph = "<p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor.</p>";
+    let (actual_tree, actual_meta) = Readability::new().parse(&paragraph.repeat(3));
+    assert_eq!(actual_meta.dir.as_deref(), Some("ltr"));
+    assert_eq!(actual_tree.as_element().unwrap().attributes.borrow().get("dir"), None);
+}
+
+#[test]
+fn attribute_allowlist() {
+    static SOURCE: &str = "<article id=\"story\" class=\"post\" data-tracking=\"1\" lang=\"en\">
+        <p style=\"color: red\" onclick=\"track()\">Lorem ipsum dolor sit amet, consectetur adipisicing elit,
+            <a href=\"/link\" class=\"internal\" title=\"Link\" data-ga=\"x\">sed do eiusmod</a> tempor.</p>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip.</p>
+        <img src=\"a.png\" alt=\"A\" width=\"10\" class=\"lazy\" onerror=\"hack()\">
+        <table><tr><th>A</th><th>B</th></tr><tr><td colspan=\"2\" class=\"cell\">Cell</td></tr></table>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    let p = actual_tree.select_first("p").unwrap();
+    assert!(p.attributes.borrow().get("style").is_none());
+    assert!(p.attributes.borrow().get("onclick").is_some());
+
+    let (actual_tree, _) = Readability::new()
+        .attribute_allowlist(AttributeAllowlist::default())
+        .parse(SOURCE);
+
+    let attrs = |selector: &str| {
+        let elem = match selector {
+            "article" => actual_tree.clone().into_element_ref().unwrap(),
+            _ => actual_tree.select_first(selector).unwrap(),
+        };
+        let attributes = elem.attributes.borrow();
+        let mut names = attributes.map.keys().map(|name| name.local.to_string()).collect::<Vec<_>>();
+        names.sort();
+        names
+    };
+
+    assert_eq!(attrs("article"), ["lang"]);
+    assert!(attrs("p").is_empty());
+    assert_eq!(attrs("a"), ["href", "title"]);
+    assert_eq!(attrs("img"), ["alt", "src", "width"]);
+    assert_eq!(attrs("td"), ["colspan"]);
+
+    let mut allowlist = AttributeAllowlist::default();
+    allowlist.allow("*", "id");
+
+    let (actual_tree, _) = Readability::new().attribute_allowlist(allowlist).parse(SOURCE);
+    assert_eq!(actual_tree.as_element().unwrap().attributes.borrow().get("id"), Some("story"));
+
+    // Classes are used for scoring, so the allowlist doesn't change the extracted content.
+    static CLASSES: &str = "<body>
+        <div>
+            <p>Ut enim ad minim veniam, quis nostrud, exercitation ullamco, laboris nisi ut aliquip ex ea.</p>
+            <p>Duis aute irure dolor in reprehenderit, in voluptate velit esse, cillum dolore eu fugiat.</p>
+            <p>Excepteur sint occaecat cupidatat non proident, sunt in culpa, qui officia deserunt mollit.</p>
+        </div>
+        <div class=\"entry-content\">
+            <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit
+                <a href=\"http://example.com\" rel=\"nofollow\">incididunt</a> ut labore.</p>
+            <p>Quis autem vel eum iure reprehenderit qui in ea voluptate velit esse quam nihil.</p>
+        </div>
+    </body>";
+
+    let (expected_tree, _) = Readability::new().parse(CLASSES);
+    assert!(expected_tree.text_contents().contains("Lorem ipsum"));
+
+    let (actual_tree, actual_meta) = Readability::new()
+        .attribute_allowlist(AttributeAllowlist::default())
+        .parse(CLASSES);
+
+    assert_eq!(actual_tree.text_contents(), expected_tree.text_contents());
+    assert_eq!(actual_meta.links[0].rel, ["nofollow"]);
+}
+
+#[test]
+fn sanitize() {
+    static SOURCE: &str = "<article>
+        <p onmouseover=\"steal()\">Lorem ipsum dolor sit amet, consectetur adipisicing elit,
+            <a href=\"javascript:steal()\">sed do eiusmod</a> tempor incididunt ut labore.</p>
+        <p>Ut enim ad minim veniam, <a href=\"https://example.com/\" target=\"_blank\">quis nostrud</a>
+            exercitation ullamco laboris nisi ut aliquip ex ea commodo consequat.</p>
+        <iframe srcdoc=\"&lt;script&gt;steal()&lt;/script&gt;\"></iframe>
+        <svg><script>steal()</script><circle r=\"1\"/></svg>
+        <img src=\"a.png\" onerror=\"steal()\">
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    assert!(actual_tree.to_string().contains("javascript:"));
+
+    let (actual_tree, _) = Readability::new().sanitize(true).parse(SOURCE);
+    let html = actual_tree.to_string();
+
+    for needle in ["javascript:", "onmouseover", "onerror", "srcdoc", "<iframe", "<svg", "<script"] {
+        assert!(!html.contains(needle), "{}", needle);
+    }
+
+    let link = actual_tree.select_first("a[target]").unwrap();
+    assert_eq!(link.attributes.borrow().get("rel"), Some("noopener noreferrer"));
+    assert_eq!(actual_tree.select("p").unwrap().count(), 2);
+    assert_eq!(actual_tree.select("img").unwrap().count(), 1);
+
+    // Kept frames must still load an allowed page.
+    let source = SOURCE.replace("<iframe srcdoc", "<iframe src=\"https://evil.example/x\"></iframe>\
+        <iframe src=\"https://www.youtube.com/embed/x\"></iframe><iframe srcdoc");
+    let (actual_tree, _) = Readability::new().keep_tag("iframe").sanitize(true).parse(&source);
+    let frames = actual_tree.select("iframe").unwrap().collect::<Vec<_>>();
+
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].attributes.borrow().get("src"), Some("https://www.youtube.com/embed/x"));
+}
+
+#[test]
+fn link_normalizer() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, <a href=\"/page?id=1&amp;utm_source=feed\">consectetur</a>
+            adipisicing elit, <a href=\"https://l.facebook.com/l.php?u=https%3A%2F%2Fexample.com%2F%3Ffbclid%3Dx\">sed</a>
+            do eiusmod <a href=\"https://t.co/abc\" data-expanded-url=\"https://example.org/story\">tempor</a>.</p>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip.</p>
+    </article>";
+
+    let links = |tree: &NodeRef| tree.select("a").unwrap()
+        .map(|a| a.attributes.borrow().get("href").unwrap().to_string())
+        .collect::<Vec<_>>();
+
+    let (actual_tree, _) = Readability::new()
+        .base_url(Url::parse("http://fakehost/test/page.html").unwrap())
+        .parse(SOURCE);
+
+    assert_eq!(links(&actual_tree)[0], "http://fakehost/page?id=1&utm_source=feed");
+
+    let (actual_tree, _) = Readability::new()
+        .base_url(Url::parse("http://fakehost/test/page.html").unwrap())
+        .link_normalizer(LinkNormalizer::default())
+        .parse(SOURCE);
+
+    assert_eq!(links(&actual_tree), [
+        "http://fakehost/page?id=1",
+        "https://example.com/",
+        "https://example.org/story",
+    ]);
+}
+
+#[test]
+fn links_and_images() {
+    static SOURCE: &str = "<body>
+        <nav><a href=\"/\">Home</a> <a href=\"/news\">News</a></nav>
+        <article>
+            <p>Lorem ipsum dolor sit amet, <a href=\"/related\">consectetur</a> adipisicing elit,
+                sed do eiusmod <a href=\"https://example.org/source\" rel=\"nofollow\">tempor</a>.</p>
+            <figure><img src=\"photo.jpg\" alt=\"Photo\" width=\"800\" height=\"600\">
+                <figcaption>A photo of the event.</figcaption></figure>
+            <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip.</p>
+        </article>
+    </body>";
+
+    let (_, metadata) = Readability::new()
+        .base_url(Url::parse("http://fakehost/test/page.html").unwrap())
+        .parse(SOURCE);
+
+    let links = metadata.links.iter()
+        .map(|link| (link.url.as_str(), &link.text[..], link.is_internal))
+        .collect::<Vec<_>>();
+
+    assert_eq!(links, [
+        ("http://fakehost/related", "consectetur", true),
+        ("https://example.org/source", "tempor", false),
+    ]);
+    assert_eq!(metadata.links[1].rel, ["nofollow"]);
+
+    assert_eq!(metadata.images.len(), 1);
+    assert_eq!(metadata.images[0].src.as_str(), "http://fakehost/test/photo.jpg");
+    assert_eq!(metadata.images[0].alt.as_deref(), Some("Photo"));
+    assert_eq!((metadata.images[0].width, metadata.images[0].height), (Some(800), Some(600)));
+    assert_eq!(metadata.images[0].caption.as_deref(), Some("A photo of the event."));
+
+    // Inventories reflect the content returned after hooks and sanitizing.
+    struct Hook;
+
+    impl ExtractionHook for Hook {
+        fn post_process(&mut self, content: &NodeRef, _metadata: &mut Metadata) {
+            content.select_first("figure").unwrap().as_node().detach();
+            content.select_first("a[href$=\"/related\"]").unwrap().as_node().detach();
+        }
+    }
+
+    let (actual_tree, metadata) = Readability::new()
+        .base_url(Url::parse("http://fakehost/test/page.html").unwrap())
+        .hook(Hook)
+        .sanitize(true)
+        .parse(SOURCE);
+
+    assert_eq!(actual_tree.select("a").unwrap().count(), metadata.links.len());
+    assert_eq!(metadata.links.len(), 1);
+    assert_eq!(metadata.links[0].rel, ["nofollow", "noopener", "noreferrer"]);
+    assert!(metadata.images.is_empty());
+}
+
+#[test]
+fn table_of_contents() {
+    static SOURCE: &str = include_sample_file!(wikipedia, "source.html");
+
+    let (actual_tree, metadata) = Readability::new().inject_heading_ids(true).parse(SOURCE);
+    assert!(!metadata.toc.is_empty());
+
+    fn check(tree: &NodeRef, entries: &[readable_readability::TocEntry], parent_level: u8) {
+        for entry in entries {
+            assert!(entry.level > parent_level);
+            let target = tree.select_first(&format!("[id=\"{}\"]", entry.id)).unwrap();
+            let heading = target.as_node().inclusive_ancestors().elements()
+                .find(|elem| elem.name.local.starts_with('h')).unwrap();
+            assert_eq!(&*heading.name.local, format!("h{}", entry.level));
+            check(tree, &entry.children, entry.level);
+        }
+    }
+
+    check(&actual_tree, &metadata.toc, 0);
+
+    // Anchors of MediaWiki are reused and edit links are skipped.
+    let entries = metadata.toc.iter()
+        .map(|entry| (&entry.text[..], &entry.id[..]))
+        .collect::<Vec<_>>();
+
+    assert_eq!(entries[..3], [("History", "History"), ("Values", "Values"), ("Software", "Software")]);
+    assert_eq!(metadata.toc[0].children[0].text, "Eich CEO promotion controversy");
+    assert_eq!(metadata.toc[0].children[0].id, "Eich_CEO_promotion_controversy");
+
+    // Headings removed by hooks aren't listed.
+    struct Hook;
+
+    impl ExtractionHook for Hook {
+        fn post_process(&mut self, content: &NodeRef, _metadata: &mut Metadata) {
+            for heading in content.select("h2, h3, h4").unwrap().collect::<Vec<_>>() {
+                heading.as_node().detach();
+            }
+        }
+    }
+
+    let (_, metadata) = Readability::new().hook(Hook).parse(SOURCE);
+    assert!(metadata.toc.is_empty());
+}
+
+#[test]
+fn code_blocks() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt.</p>
+        <div class=\"language-rust highlighter-rouge\"><div class=\"highlight\"><pre class=\"highlight\"><code><span class=\"k\">fn</span> <span class=\"nf\">main</span><span class=\"p\">()</span> <span class=\"p\">{</span>
+    <span class=\"nd\">println!</span><span class=\"p\">(</span><span class=\"s\">\"hi\"</span><span class=\"p\">);</span>
+<span class=\"p\">}</span>
+</code></pre></div></div>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea.</p>
+        <div class=\"highlight\"><table class=\"highlighttable\"><tr>
+            <td class=\"linenos\"><div class=\"linenodiv\"><pre>1
+2</pre></div></td>
+            <td class=\"code\"><div class=\"highlight\"><pre><span></span><span class=\"n\">x</span> <span class=\"o\">=</span> <span class=\"mi\">1</span>
+<span class=\"n\">y</span> <span class=\"o\">=</span> <span class=\"mi\">2</span>
+</pre></div></td>
+        </tr></table></div>
+        <p>Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat.</p>
+        <div><pre>a <b>b</b> <i>c</i></pre></div>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+
+    let blocks = actual_tree.select("pre").unwrap()
+        .map(|pre| pre.as_node().to_string())
+        .collect::<Vec<_>>();
+
+    assert_eq!(blocks, [
+        "<pre><code class=\"language-rust\">fn main() {\n    println!(\"hi\");\n}\n</code></pre>",
+        "<pre><code>x = 1\ny = 2\n</code></pre>",
+        "<pre>a <b>b</b> <i>c</i></pre>",
+    ]);
+}
+
+#[test]
+fn inline_code() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt
+            <code><span class=\"k\">let</span> <span class=\"n\">x</span> <span class=\"o\">=</span> <span class=\"mi\">1</span><span class=\"comment\"> // one</span></code>.</p>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea.</p>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+    let code = actual_tree.select_first("code").expect("the code is removed");
+    assert_eq!(code.text_contents(), "let x = 1 // one");
+}
+
+#[test]
+fn code_blocks_with_line_numbers() {
+    static PRISM: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt.</p>
+        <pre class=\"line-numbers language-js\"><code>let x = 1;<span class=\"line-numbers-rows\"><span></span></span></code></pre>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea.</p>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(PRISM);
+    let pre = actual_tree.select_first("pre").expect("the code block is removed");
+    assert_eq!(pre.as_node().to_string(), "<pre><code class=\"language-js\">let x = 1;</code></pre>");
+
+    static LAYOUT: &str = "<table><tr><td class=\"gutter\"></td><td><article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt.</p>
+        <pre>code</pre>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea.</p>
+    </article></td></tr></table>";
+
+    let (actual_tree, _) = Readability::new().parse(LAYOUT);
+    let text = actual_tree.text_contents();
+    assert!(text.contains("Lorem ipsum") && text.contains("Ut enim"), "{}", text);
+    assert!(actual_tree.select_first("pre").is_ok());
+}
+
+#[test]
+fn math() {
+    static SOURCE: &str = "<article>
+        <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt
+            <span class=\"katex\"><span class=\"katex-mathml\"><math><semantics><mrow><mi>E</mi><mo>=</mo><mi>m</mi><msup><mi>c</mi><mn>2</mn></msup></mrow>
+            <annotation encoding=\"application/x-tex\">E = mc^2</annotation></semantics></math></span>
+            <span class=\"katex-html\" aria-hidden=\"true\"><span class=\"mord\">E</span></span></span>.</p>
+        <div><script type=\"math/tex; mode=display\">\\int_0^1 x\\,dx</script></div>
+        <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea.</p>
+    </article>";
+
+    let (actual_tree, _) = Readability::new().parse(SOURCE);
+
+    let formulas = actual_tree.select("annotation").unwrap()
+        .map(|annotation| annotation.text_contents())
+        .collect::<Vec<_>>();
+
+    assert_eq!(formulas, ["E = mc^2", "\\int_0^1 x\\,dx"]);
+    assert!(actual_tree.select_first(".katex-html").is_err());
+
+    let (actual_tree, _) = Readability::new().sanitize(true).parse(SOURCE);
+    assert_eq!(actual_tree.select("math").unwrap().count(), 2);
+}
+
+#[test]
+fn footnotes() {
+    static SOURCE: &str = "<body>
+        <div class=\"post\">
+            <p>Lorem ipsum dolor sit amet, consectetur adipisicing elit, sed do eiusmod tempor incididunt ut labore
+                et dolore magna aliqua.<sup id=\"fnref:1\"><a href=\"#fn:1\" class=\"footnote\">1</a></sup></p>
+            <p>Ut enim ad minim veniam, quis nostrud exercitation ullamco laboris nisi ut aliquip ex ea commodo
+                consequat.<sup id=\"fnref:2\"><a href=\"#fn:2\" class=\"footnote\">2</a></sup></p>
+            <p>Duis aute irure dolor in reprehenderit in voluptate velit esse cillum dolore eu fugiat nulla pariatur.</p>
+        </div>
+        <div class=\"footnotes\" role=\"doc-endnotes\">
+            <ol>
+                <li id=\"fn:1\"><p><a href=\"https://example.com/1\">Source one</a>. <a href=\"#fnref:1\" class=\"reversefootnote\">↩</a></p></li>
+                <li id=\"fn:2\"><p><a href=\"https://example.com/2\">Source two</a>. <a href=\"#fnref:2\" class=\"reversefootnote\">↩</a></p></li>
+            </ol>
+        </div>
+    </body>";
+
+    let (actual_tree, metadata) = Readability::new()
+        .attribute_allowlist(AttributeAllowlist::default())
+        .parse(SOURCE);
+
+    let last = actual_tree.children().rev().find(|node| node.as_element().is_some()).unwrap();
+    assert_eq!(last.select("li").unwrap().count(), 2);
+
+    assert!(actual_tree.select_first("[id=\"fn:1\"]").is_ok());
+    assert!(actual_tree.select_first("[id=\"fnref:2\"]").is_ok());
+
+    let footnotes = metadata.footnotes.iter()
+        .map(|footnote| (&footnote.id[..], &footnote.label[..], &footnote.text[..]))
+        .collect::<Vec<_>>();
+
+    assert_eq!(footnotes, [("fn:1", "1", "Source one."), ("fn:2", "2", "Source two.")]);
+
+    let source = SOURCE.replace("href=\"https://example.com/2\"", "href=\"javascript:steal()\" onclick=\"x()\"");
+    let (_, metadata) = Readability::new().sanitize(true).parse(&source);
+
+    assert_eq!(metadata.footnotes.len(), 2);
+    assert!(!metadata.footnotes[1].html.contains("javascript:"));
+    assert!(!metadata.footnotes[1].html.contains("onclick"));
+}